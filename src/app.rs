@@ -1,11 +1,28 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
 use std::path::PathBuf;
-use std::fs;
 use std::io;
-use std::io::Seek;
+use std::mem;
+use std::process::Command;
+use std::time::{Duration, Instant};
 use walkdir::WalkDir;
-use notify::{Watcher, RecursiveMode, Event, Result as NotifyResult};
-use crate::log_parser::{LogParser, LogEntry};
-use crate::ui::{UI, UIEvent};
+use crate::bookmarks::{BookmarkList, Bookmarks};
+use crate::columns::{self, ColumnLayouts, LogFormat, SortKeys};
+use crate::config::{Config, RetentionConfig};
+use crate::fields;
+use crate::filter::EntryFilter;
+use crate::fuzzy::FileFinder;
+use crate::ingest::{Ingest, Ingested};
+use crate::log_parser::{self, LogEntry, LogParser};
+use crate::stats::LogStats;
+use crate::expansions::Expansions;
+use crate::export::{self, ExportFormat};
+use crate::goto;
+use crate::keymap::Keymap;
+use crate::query::{self, QueryView};
+use crate::retention::{self, Evicted, Retention};
+use crate::session::Session;
+use crate::ui::{LineNumbers, Prompt, PromptKind, StatusLine, UI, UIEvent};
 
 pub struct LogViewer {
     directory: PathBuf,
@@ -14,15 +31,65 @@ pub struct LogViewer {
     log_entries: Vec<LogEntry>,
     ui: UI,
     parser: LogParser,
+    stats: LogStats,
+    /// Statistics of the resident entries the filter shows.
+    filtered_stats: LogStats,
+    bookmarks: Bookmarks,
+    expansions: Expansions,
+    entry_marks: HashMap<usize, char>,
+    column_layouts: ColumnLayouts,
+    /// Keys of the sort column, kept while a sort is active.
+    sort_keys: SortKeys,
+    log_format: LogFormat,
+    filter: EntryFilter,
+    session: Session,
     is_tailing: bool,
     is_file_list_focused: bool,
     last_file_size: u64,
+    ingested: VecDeque<(Instant, usize)>,
+    ingest: Option<Ingest>,
+    retention_config: RetentionConfig,
+    retention: Retention,
+    evicted: Evicted,
+    resident_bytes: u64,
 }
 
+/// Window over which the ingest rate in the status bar is averaged.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// Longest the UI spends taking in entries before it looks at input again.
+const INGEST_BUDGET: Duration = Duration::from_millis(50);
+
+/// Shortest time between redraws for entries arriving.
+const FRAME_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Longest time between redraws, which keeps the rate and messages current.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long to wait for input when there is nothing else to do.
+const INPUT_POLL: Duration = Duration::from_millis(100);
+
+/// Columns the file list grows or shrinks by per key press.
+const FILE_LIST_STEP: i16 = 4;
+
 impl LogViewer {
-    pub fn new(directory: PathBuf) -> Result<Self, io::Error> {
-        let ui = UI::new()?;
-        let parser = LogParser::new();
+    pub fn new(directory: PathBuf, filter: EntryFilter, config: &Config, keymap: Keymap, parser: LogParser) -> Result<Self, io::Error> {
+        let mut ui = UI::new()?;
+        ui.keymap = keymap;
+        ui.wrap_lines = config.display.wrap_lines;
+        if config.display.show_line_numbers {
+            ui.line_numbers = LineNumbers::Lines;
+        }
+        if let Some(width) = config.display.file_list_min_width {
+            ui.file_list_min_width = width;
+        }
+        if let Some(width) = config.display.file_list_max_width {
+            ui.file_list_max_width = width;
+        }
+        let session = Session::load();
+        ui.file_list_width = session.file_list_width.or(config.display.file_list_width);
+        ui.file_list_hidden = session.file_list_hidden;
+        ui.auto_expand_errors = session.auto_expand_errors;
         
         let mut viewer = Self {
             directory,
@@ -31,9 +98,25 @@ impl LogViewer {
             log_entries: Vec::new(),
             ui,
             parser,
+            stats: LogStats::new(),
+            filtered_stats: LogStats::new(),
+            bookmarks: Bookmarks::load(),
+            expansions: Expansions::load(),
+            entry_marks: HashMap::new(),
+            column_layouts: ColumnLayouts::load(),
+            sort_keys: SortKeys::default(),
+            log_format: LogFormat::Text,
+            filter,
             is_tailing: true,
-            is_file_list_focused: true,
+            is_file_list_focused: !session.file_list_hidden,
+            session,
             last_file_size: 0,
+            ingested: VecDeque::new(),
+            ingest: None,
+            retention_config: config.retention.clone(),
+            retention: Retention::default(),
+            evicted: Evicted::default(),
+            resident_bytes: 0,
         };
 
         viewer.load_files()?;
@@ -50,104 +133,150 @@ impl LogViewer {
         Ok(())
     }
 
+    /// Switches to a file. Its entries are read and parsed on the ingest
+    /// thread and arrive through `receive_entries`.
     fn load_log_file(&mut self, file: &PathBuf) -> io::Result<()> {
-        let content = fs::read_to_string(file)?;
-        self.log_entries = self.parser.parse(&content);
+        self.ingest = Some(Ingest::start(file, self.parser.clone())?);
+        self.log_entries.clear();
+        self.retention = Retention::for_file(&self.retention_config, file);
+        self.evicted = Evicted::default();
+        self.resident_bytes = 0;
+        self.ui.file_start = (1, 0);
+        // Expansion and sort order refer to the previous file's entries
+        self.ui.clear_expanded_entries();
+        self.ui.expansion = self.expansions.for_file(file);
+        self.ui.row_order.clear();
+        self.sort_keys = SortKeys::default();
+        self.stats.clear();
         self.current_file = Some(file.clone());
-        self.last_file_size = file.metadata()?.len();
-        
+        self.last_file_size = 0;
+        self.ingested.clear();
+        self.refresh_marks();
+        self.apply_filter();
+        self.detect_format();
+
         // Reset UI state for the new file
         self.ui.log_list_state.select(None);
-        self.ui.clear_expanded_entries();
         self.ui.reset_scroll();
         self.is_tailing = true;
-        
-        // If we have entries, select the last one by default
-        if !self.log_entries.is_empty() {
-            self.ui.log_list_state.select(Some(self.log_entries.len() - 1));
-        }
-        
         Ok(())
     }
 
-    fn handle_file_list_navigation(&mut self, up: bool) -> io::Result<()> {
+    /// Picks the log format and its column layout from the entries so far.
+    fn detect_format(&mut self) {
+        self.log_format = LogFormat::detect(&self.log_entries);
+        self.ui.columns = self.column_layouts
+            .get(self.log_format)
+            .cloned()
+            .unwrap_or_else(|| columns::default_columns(self.log_format, &self.log_entries));
+        self.apply_sort();
+    }
+
+    /// Loads a file, reporting a failure in the status bar rather than quitting.
+    fn open_file(&mut self, file: &PathBuf) {
+        if let Err(e) = self.load_log_file(file) {
+            self.ui.show_error(format!("{}: {}", file.display(), e));
+        }
+    }
+
+    /// Moves the file selection by `delta` files, opening the file it lands on.
+    fn handle_file_list_navigation(&mut self, delta: isize) -> io::Result<()> {
+        if self.files.is_empty() {
+            return Ok(());
+        }
         let selected = self.ui.file_list_state.selected();
         let new_selected = match selected {
-            Some(selected) => {
-                if up {
-                    if selected > 0 { selected - 1 } else { selected }
-                } else {
-                    if selected < self.files.len() - 1 { selected + 1 } else { selected }
-                }
-            }
-            None => if !self.files.is_empty() { 0 } else { 0 },
+            Some(selected) => selected.saturating_add_signed(delta).min(self.files.len() - 1),
+            None => 0,
         };
 
         if new_selected != selected.unwrap_or(0) {
             self.ui.file_list_state.select(Some(new_selected));
             let file = self.files[new_selected].clone();
-            self.load_log_file(&file)?;
+            self.open_file(&file);
         }
         Ok(())
     }
 
-    fn handle_log_list_navigation(&mut self, up: bool) -> io::Result<()> {
+    /// Moves the log selection by `delta` visible lines.
+    fn handle_log_list_navigation(&mut self, delta: isize) -> io::Result<()> {
+        // Scrolling back past the first resident entry brings evicted ones back
+        let selected = self.ui.log_list_state.selected();
+        if delta < 0 && self.evicted.entries > 0 && selected.is_some_and(|selected| selected < delta.unsigned_abs()) {
+            self.reload_evicted();
+        }
+
         let selected = self.ui.log_list_state.selected();
         
         // Calculate total number of visible lines including expanded entries
-        let total_visible_lines = self.log_entries.iter().enumerate()
-            .map(|(i, entry)| {
-                if self.ui.expanded_entries.contains(&i) {
-                    entry.lines.len()
-                } else {
-                    1
-                }
-            })
-            .sum::<usize>();
+        let total_visible_lines = self.ui.total_lines();
+        if total_visible_lines == 0 {
+            return Ok(());
+        }
 
-        let new_selected = match selected {
-            Some(selected) => {
-                if up {
-                    if selected > 0 { selected - 1 } else { selected }
-                } else {
-                    if selected < total_visible_lines - 1 { selected + 1 } else { selected }
-                }
-            }
-            None => if !self.log_entries.is_empty() { 0 } else { 0 },
-        };
+        // Nothing selected yet is the bottom, where tail mode keeps the selection
+        let selected = selected.unwrap_or(total_visible_lines - 1);
+        let new_selected = selected.saturating_add_signed(delta).min(total_visible_lines - 1);
+        // Selecting the same line still matters: on the last one it resumes tail mode
+        self.select_line(new_selected);
+        Ok(())
+    }
 
-        if new_selected != selected.unwrap_or(0) {
-            self.ui.log_list_state.select(Some(new_selected));
-            // Only disable tail mode if we're not at the last entry
-            if new_selected < total_visible_lines - 1 {
-                self.is_tailing = false;
-            } else {
-                self.is_tailing = true;
-            }
+    /// Moves the selection of the focused list by `delta` lines.
+    fn move_selection(&mut self, delta: isize) -> io::Result<()> {
+        if self.is_file_list_focused {
+            self.handle_file_list_navigation(delta)
+        } else {
+            self.handle_log_list_navigation(delta)
         }
-        Ok(())
     }
 
-    fn handle_navigation(&mut self, event: UIEvent) -> io::Result<()> {
+    /// Handles a key event outside popups. `count` is the number typed
+    /// before the key: motions repeat that many times, and top/bottom go to
+    /// that line (or file) instead.
+    fn handle_navigation(&mut self, event: UIEvent, count: Option<usize>) -> io::Result<()> {
+        let repeat = count.map_or(1, |count| isize::try_from(count).unwrap_or(isize::MAX));
+        let page = self.ui.log_view_height.max(1) as isize;
         match event {
-            UIEvent::Up => {
-                if self.is_file_list_focused {
-                    self.handle_file_list_navigation(true)?;
-                } else {
-                    self.handle_log_list_navigation(true)?;
-                }
+            UIEvent::Up if self.ui.details_focused => {
+                self.ui.detail_field = self.ui.detail_field.saturating_sub(repeat as usize);
             }
-            UIEvent::Down => {
-                if self.is_file_list_focused {
-                    self.handle_file_list_navigation(false)?;
-                } else {
-                    self.handle_log_list_navigation(false)?;
-                }
+            UIEvent::Down if self.ui.details_focused => {
+                let field_count = self.selected_fields().len();
+                self.ui.detail_field = self.ui.detail_field.saturating_add(repeat as usize).min(field_count.saturating_sub(1));
             }
+            UIEvent::Left | UIEvent::SwitchToFileList if self.ui.details_focused => {
+                self.ui.details_focused = false;
+            }
+            UIEvent::Up => self.move_selection(-repeat)?,
+            UIEvent::Down => self.move_selection(repeat)?,
+            UIEvent::PageUp => self.move_selection(-page.saturating_mul(repeat))?,
+            UIEvent::PageDown => self.move_selection(page.saturating_mul(repeat))?,
+            UIEvent::HalfPageUp => self.move_selection(-(page / 2).max(1).saturating_mul(repeat))?,
+            UIEvent::HalfPageDown => self.move_selection((page / 2).max(1).saturating_mul(repeat))?,
+            UIEvent::Top | UIEvent::Bottom if self.is_file_list_focused => {
+                let target = match (count, event) {
+                    (Some(n), _) => n.saturating_sub(1) as isize,
+                    (None, UIEvent::Top) => 0,
+                    (None, _) => isize::MAX,
+                };
+                let current = self.ui.file_list_state.selected().unwrap_or(0) as isize;
+                self.handle_file_list_navigation(target.saturating_sub(current))?;
+            }
+            UIEvent::Top | UIEvent::Bottom => match count {
+                // Like vim's `42G`: a count picks the file line
+                Some(line) => {
+                    if let Err(error) = self.goto(&line.to_string()) {
+                        self.ui.show_error(error);
+                    }
+                }
+                None if event == UIEvent::Top => self.handle_log_list_navigation(isize::MIN)?,
+                None => self.handle_log_list_navigation(isize::MAX)?,
+            },
             UIEvent::Left => {
                 if self.is_file_list_focused {
                     // Do nothing in file list
-                } else if self.ui.is_at_beginning() {
+                } else if self.ui.is_at_beginning() && !self.ui.file_list_hidden {
                     // If at the beginning of the lines, switch to file list
                     self.is_file_list_focused = true;
                 } else {
@@ -164,109 +293,969 @@ impl LogViewer {
             }
             UIEvent::SwitchToFileList => {
                 self.is_file_list_focused = true;
+                // Going back to a hidden file list brings it back
+                if self.ui.file_list_hidden {
+                    self.ui.file_list_hidden = false;
+                    self.save_session();
+                }
+            }
+            UIEvent::GrowFileList | UIEvent::ShrinkFileList => {
+                let step = FILE_LIST_STEP.saturating_mul(repeat.min(i16::MAX as isize) as i16);
+                self.ui.resize_file_list(if event == UIEvent::GrowFileList { step } else { -step });
+                self.ui.file_list_hidden = false;
+                self.save_session();
+            }
+            UIEvent::ToggleFileList => {
+                self.ui.file_list_hidden = !self.ui.file_list_hidden;
+                self.is_file_list_focused = !self.ui.file_list_hidden && self.is_file_list_focused;
+                self.save_session();
+            }
+            UIEvent::FileListResized => self.save_session(),
+            UIEvent::SelectFile(index) if index < self.files.len() => {
+                self.is_file_list_focused = true;
+                self.ui.details_focused = false;
+                if self.ui.file_list_state.selected() != Some(index) {
+                    self.ui.file_list_state.select(Some(index));
+                    let file = self.files[index].clone();
+                    self.open_file(&file);
+                }
+            }
+            UIEvent::SelectLine(line) => {
+                self.is_file_list_focused = false;
+                self.ui.details_focused = false;
+                self.select_line(line);
+            }
+            UIEvent::ScrollFiles(lines) => self.handle_file_list_navigation(lines)?,
+            UIEvent::ScrollLines(lines) => self.handle_log_list_navigation(lines)?,
+            UIEvent::JumpToBucket(bucket) => {
+                let first_entry = self.ui
+                    .timeline(&self.log_entries)
+                    .and_then(|timeline| timeline.buckets.get(bucket).and_then(|b| b.first_entry));
+                if let Some(entry) = first_entry {
+                    self.is_file_list_focused = false;
+                    self.select_entry(entry);
+                }
             }
             UIEvent::SwitchToLogView => {
                 self.is_file_list_focused = false;
             }
-            UIEvent::ToggleExpand => {
-                if !self.is_file_list_focused {
-                    if let Some(selected_line) = self.ui.log_list_state.selected() {
-                        // Map the selected line index back to the log entry index
-                        let mut entry_index = 0;
-                        let mut line_count = 0;
-                        for (i, entry) in self.log_entries.iter().enumerate() {
-                            let is_expanded = self.ui.expanded_entries.contains(&i);
-                            let lines = if is_expanded { entry.lines.len() } else { 1 };
-                            if selected_line < line_count + lines {
-                                entry_index = i;
-                                break;
-                            }
-                            line_count += lines;
-                        }
-                        self.ui.toggle_expand(entry_index);
+            UIEvent::NextLevel(level) | UIEvent::PreviousLevel(level) if !self.is_file_list_focused => {
+                let forward = matches!(event, UIEvent::NextLevel(_));
+                self.jump_to_entry(forward, repeat, |entry| entry.level == level);
+            }
+            UIEvent::NextMultiline | UIEvent::PreviousMultiline if !self.is_file_list_focused => {
+                let forward = event == UIEvent::NextMultiline;
+                self.jump_to_entry(forward, repeat, |entry| entry.lines.len() > 1);
+            }
+            UIEvent::ToggleExpand if !self.is_file_list_focused => {
+                if let Some(selected_line) = self.ui.log_list_state.selected() {
+                    let entry_index = self.ui.entry_at_line(selected_line);
+                    self.ui.toggle_expand(&self.log_entries, entry_index);
+                    self.save_expansion();
+                }
+            }
+            UIEvent::ExpandAll | UIEvent::CollapseAll | UIEvent::ToggleAutoExpandErrors if !self.is_file_list_focused => {
+                let selected = self.selected_entry();
+                match event {
+                    UIEvent::ExpandAll => self.ui.expand_all(&self.log_entries),
+                    UIEvent::CollapseAll => self.ui.collapse_all(&self.log_entries),
+                    _ => {
+                        self.ui.toggle_auto_expand_errors(&self.log_entries);
+                        let state = if self.ui.auto_expand_errors { "on" } else { "off" };
+                        self.ui.show_message(format!("auto-expand errors {}", state));
                     }
                 }
+                self.save_expansion();
+                self.save_session();
+                if self.is_tailing {
+                    self.select_last_line();
+                } else if !self.log_entries.is_empty() {
+                    self.select_entry(selected);
+                }
             }
             UIEvent::ToggleTail => {
-                if !self.is_file_list_focused {
-                    self.is_tailing = !self.is_tailing;
-                    if self.is_tailing && !self.log_entries.is_empty() {
-                        self.ui.log_list_state.select(Some(self.log_entries.len() - 1));
-                    }
+                self.is_tailing = !self.is_tailing;
+                if self.is_tailing {
+                    self.select_last_line();
                 }
             }
-            UIEvent::ScrollLeft => {
-                if !self.is_file_list_focused {
+            UIEvent::ScrollLeft if !self.is_file_list_focused => {
+                for _ in 0..repeat.min(1000) {
                     self.ui.scroll_log_left();
                 }
             }
-            UIEvent::ScrollRight => {
-                if !self.is_file_list_focused {
+            UIEvent::ScrollRight if !self.is_file_list_focused => {
+                for _ in 0..repeat.min(1000) {
                     self.ui.scroll_log_right();
                 }
             }
+            UIEvent::ToggleDetails => {
+                self.ui.show_details = !self.ui.show_details;
+                self.ui.details_focused = false;
+            }
+            UIEvent::FocusDetails => {
+                // Tab moves between the log view and the details pane when it is shown
+                if self.ui.show_details && !self.ui.details_focused {
+                    self.ui.details_focused = true;
+                    self.is_file_list_focused = false;
+                    self.ui.detail_field = 0;
+                } else {
+                    self.ui.details_focused = false;
+                }
+            }
+            UIEvent::FilterByField if self.ui.details_focused => {
+                if let Some(field) = self.selected_fields().into_iter().nth(self.ui.detail_field) {
+                    if !self.filter.fields.contains(&field) {
+                        self.filter.fields.push(field);
+                        self.refilter();
+                    }
+                }
+            }
+            UIEvent::ClearFilter if self.filter.is_active() => {
+                self.filter = EntryFilter::default();
+                self.refilter();
+            }
+            UIEvent::ToggleWrap => {
+                self.ui.wrap_lines = !self.ui.wrap_lines;
+                self.ui.reset_scroll();
+            }
+            UIEvent::CycleLineNumbers => {
+                self.ui.line_numbers = match self.ui.line_numbers {
+                    LineNumbers::Off => LineNumbers::Lines,
+                    LineNumbers::Lines => LineNumbers::Offsets,
+                    LineNumbers::Offsets => LineNumbers::Off,
+                };
+            }
+            UIEvent::OpenInEditor if !self.is_file_list_focused => self.open_in_editor()?,
+            UIEvent::ToggleColumns => {
+                self.ui.show_columns = !self.ui.show_columns;
+                // Sorting is part of the column view
+                if !self.ui.show_columns && self.ui.column_sort.is_some() {
+                    self.sort_by(None);
+                }
+            }
+            UIEvent::OpenColumns => {
+                let input = columns::format_columns(&self.ui.columns);
+                self.ui.prompt = Some(Prompt::with_input(PromptKind::Columns(self.log_format.name()), input));
+                self.ui.show_columns = true;
+            }
+            UIEvent::OpenSort => {
+                let input = match &self.ui.column_sort {
+                    Some((column, true)) => format!("{} desc", column),
+                    Some((column, false)) => column.clone(),
+                    None => String::new(),
+                };
+                self.ui.prompt = Some(Prompt::with_input(PromptKind::Sort, input));
+                self.ui.show_columns = true;
+            }
+            UIEvent::ToggleStats => {
+                self.ui.show_stats = !self.ui.show_stats;
+            }
+            UIEvent::ToggleTimeline => {
+                self.ui.show_timeline = !self.ui.show_timeline;
+            }
+            UIEvent::PreviousTimeBucket if !self.is_file_list_focused => {
+                for _ in 0..repeat.min(1000) {
+                    self.jump_to_time_bucket(false);
+                }
+            }
+            UIEvent::NextTimeBucket if !self.is_file_list_focused => {
+                for _ in 0..repeat.min(1000) {
+                    self.jump_to_time_bucket(true);
+                }
+            }
+            UIEvent::OpenGoto if !self.log_entries.is_empty() => {
+                self.is_file_list_focused = false;
+                self.ui.prompt = Some(Prompt::new(PromptKind::Goto));
+            }
+            UIEvent::OpenQuery => {
+                self.open_query_prompt();
+            }
+            UIEvent::SetMark(mark) if !self.log_entries.is_empty() => {
+                self.set_mark(mark);
+            }
+            UIEvent::JumpToMark(mark) => {
+                self.jump_to_mark(mark);
+            }
+            UIEvent::OpenBookmarks if self.current_file.is_some() => {
+                self.open_bookmark_list();
+            }
+            UIEvent::ToggleSelection if !self.is_file_list_focused && !self.log_entries.is_empty() => {
+                self.ui.selection_anchor = match self.ui.selection_anchor {
+                    Some(_) => None,
+                    None => Some(self.selected_entry()),
+                };
+            }
+            UIEvent::OpenExport if !self.log_entries.is_empty() => {
+                self.ui.prompt = Some(Prompt::new(PromptKind::Export(self.ui.export_entries().len())));
+            }
+            UIEvent::OpenFileFinder => {
+                self.ui.file_finder = Some(FileFinder::new(&self.files, &self.directory));
+            }
             _ => {}
         }
         Ok(())
     }
 
-    fn handle_file_update(&mut self) -> io::Result<()> {
-        if let Some(file) = &self.current_file {
-            let current_size = file.metadata()?.len();
-            if current_size > self.last_file_size {
-                // Read only the new content
-                let mut file = fs::File::open(file)?;
-                file.seek(io::SeekFrom::Start(self.last_file_size))?;
-                let mut new_content = String::new();
-                io::Read::read_to_string(&mut file, &mut new_content)?;
-                
-                // Parse new entries
-                let new_entries = self.parser.parse(&new_content);
-                self.log_entries.extend(new_entries);
-                
-                // Update last file size
-                self.last_file_size = current_size;
-                
-                // If we're in tail mode, select the last entry
-                if self.is_tailing && !self.log_entries.is_empty() {
-                    self.ui.log_list_state.select(Some(self.log_entries.len() - 1));
+    /// Selects the first entry of the previous or next non-empty timeline bucket.
+    fn jump_to_time_bucket(&mut self, forward: bool) {
+        let selected_entry = self.selected_entry();
+        let Some(timeline) = self.ui.timeline(&self.log_entries) else {
+            return;
+        };
+        // Untimestamped entries (continuations, preambles) use the nearest earlier timestamp
+        let current_bucket = self.log_entries[..=selected_entry.min(self.log_entries.len() - 1)]
+            .iter()
+            .rev()
+            .find_map(|e| e.time)
+            .map(|t| timeline.bucket_of(t));
+
+        let target = match current_bucket {
+            Some(bucket) => timeline.next_bucket(bucket, forward),
+            None if forward => timeline.next_bucket(0, true).or(Some(0)),
+            None => None,
+        };
+        match target.and_then(|b| timeline.buckets[b].first_entry) {
+            Some(entry) => self.select_entry(entry),
+            // The timeline only spans resident entries; earlier ones come back first
+            None if !forward && self.evicted.entries > 0 && self.reload_evicted() => self.jump_to_time_bucket(false),
+            None => {}
+        }
+    }
+
+    /// Selects the `count`th next (or previous) shown entry matching `predicate`,
+    /// or the furthest one if there are fewer.
+    fn jump_to_entry(&mut self, forward: bool, count: isize, predicate: impl Fn(&LogEntry) -> bool) {
+        let mut entry = self.selected_entry();
+        let mut found = None;
+        for _ in 0..count {
+            match self.ui.find_entry(&self.log_entries, entry, forward, &predicate) {
+                Some(next) => {
+                    entry = next;
+                    found = Some(next);
+                }
+                None => break,
+            }
+        }
+        match found {
+            Some(entry) => self.select_entry(entry),
+            None => self.ui.show_message(if forward { "no further entries" } else { "no earlier entries" }),
+        }
+    }
+
+    fn handle_prompt(&mut self, event: UIEvent) -> io::Result<()> {
+        let Some(prompt) = self.ui.prompt.as_mut() else {
+            return Ok(());
+        };
+        // Yes/no prompts act on a single key press
+        let event = match (&prompt.kind, event) {
+            (PromptKind::ConfirmOverwrite(_), UIEvent::Input('y')) => UIEvent::Confirm,
+            (PromptKind::ConfirmOverwrite(_), UIEvent::Input('n')) => UIEvent::Cancel,
+            (PromptKind::ConfirmOverwrite(_), UIEvent::Input(_) | UIEvent::Backspace) => return Ok(()),
+            (_, event) => event,
+        };
+        match event {
+            UIEvent::Input(c) => {
+                prompt.input.push(c);
+                prompt.error = None;
+            }
+            UIEvent::Backspace => {
+                prompt.input.pop();
+                prompt.error = None;
+            }
+            UIEvent::Cancel => self.ui.prompt = None,
+            UIEvent::Confirm => {
+                // Take the prompt down first; actions may open a follow-up prompt
+                let Some(mut prompt) = self.ui.prompt.take() else {
+                    return Ok(());
+                };
+                let input = prompt.input.clone();
+                let result = match &prompt.kind {
+                    PromptKind::Goto => self.goto(&input),
+                    PromptKind::Query => self.run_query(&input),
+                    PromptKind::BookmarkNote(mark) => {
+                        if let Some(file) = self.current_file.clone() {
+                            self.bookmarks.set_note(&file, *mark, &input);
+                            self.save_bookmarks();
+                        }
+                        Ok(())
+                    }
+                    PromptKind::Export(_) => self.export(&input),
+                    PromptKind::ConfirmOverwrite(path) => self.export_to(path),
+                    PromptKind::Columns(_) => self.set_columns(&input),
+                    PromptKind::Sort => self.sort(&input),
+                };
+                if let Err(error) = result {
+                    prompt.error = Some(error);
+                    self.ui.prompt = Some(prompt);
                 }
             }
+            _ => {}
         }
         Ok(())
     }
 
-    pub fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        // Set up file watcher
-        let mut watcher: notify::RecommendedWatcher = notify::recommended_watcher(|res: NotifyResult<Event>| {
-            if let Ok(event) = res {
-                if let notify::EventKind::Modify(_) = event.kind {
-                    // The file has been modified, we'll handle it in the main loop
+    /// Selects the entry matching a goto prompt input (line number, time or offset).
+    fn goto(&mut self, input: &str) -> Result<(), String> {
+        let target = goto::parse_goto(input)?;
+        let mut entry = goto::resolve(&self.log_entries, self.evicted.first_line(), &target, self.selected_entry())?;
+        // Targets before the first resident entry land on it; bring back
+        // the evicted ones until the target is among them
+        while entry == 0 && self.evicted.entries > 0 && self.reload_evicted() {
+            entry = goto::resolve(&self.log_entries, self.evicted.first_line(), &target, self.selected_entry())?;
+        }
+        self.select_entry(entry);
+        Ok(())
+    }
+
+    /// Opens the query prompt, pre-filled with the last query if its results are showing.
+    fn open_query_prompt(&mut self) {
+        let input = self.ui.query_view.take().map(|view| view.sql).unwrap_or_default();
+        self.ui.prompt = Some(Prompt::with_input(PromptKind::Query, input));
+    }
+
+    fn run_query(&mut self, sql: &str) -> Result<(), String> {
+        let file = self.current_file.as_ref().map(|f| f.display().to_string()).unwrap_or_default();
+        let rows = query::rows(&self.log_entries, &file, self.evicted.first_line());
+        let result = query::run(sql, &rows)?;
+        self.ui.query_view = Some(QueryView::new(sql.trim().to_string(), result));
+        Ok(())
+    }
+
+    fn handle_query_view(&mut self, event: UIEvent) {
+        let Some(view) = self.ui.query_view.as_mut() else {
+            return;
+        };
+        match event {
+            UIEvent::Up => view.select_previous(),
+            UIEvent::Down => view.select_next(),
+            // Edit the query again
+            UIEvent::Input(';') | UIEvent::Confirm => self.open_query_prompt(),
+            UIEvent::Cancel | UIEvent::Input('q') => self.ui.query_view = None,
+            _ => {}
+        }
+    }
+
+    /// Handles the export prompt, asking for confirmation before overwriting a file.
+    fn export(&mut self, input: &str) -> Result<(), String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err("no output path given".to_string());
+        }
+        let path = match (input.strip_prefix("~/"), std::env::var_os("HOME")) {
+            (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+            _ => PathBuf::from(input),
+        };
+        if path.exists() {
+            self.ui.prompt = Some(Prompt::new(PromptKind::ConfirmOverwrite(path)));
+            return Ok(());
+        }
+        self.export_to(&path)
+    }
+
+    fn export_to(&mut self, path: &std::path::Path) -> Result<(), String> {
+        let entries: Vec<&LogEntry> = self.ui.export_entries().into_iter().map(|i| &self.log_entries[i]).collect();
+        let format = ExportFormat::from_path(path);
+        export::export(&entries, path, format)
+            .map_err(|e| format!("failed to write {} as {}: {}", path.display(), format.name(), e))?;
+        let message = if self.ui.selection_anchor.is_none() && self.evicted.entries > 0 {
+            format!(
+                "exported {} entries to {}; the {} evicted before line {} are not included",
+                entries.len(),
+                path.display(),
+                self.evicted.entries,
+                self.evicted.first_line()
+            )
+        } else {
+            format!("exported {} entries to {}", entries.len(), path.display())
+        };
+        self.ui.selection_anchor = None;
+        self.ui.show_message(message);
+        Ok(())
+    }
+
+    /// Opens the selected line in `$VISUAL` or `$EDITOR` (falling back to
+    /// `vi`) at its position in the file, then takes the terminal back.
+    fn open_in_editor(&mut self) -> io::Result<()> {
+        let Some(file) = self.current_file.clone() else {
+            return Ok(());
+        };
+        let entry = self.selected_entry();
+        let selected_line = self.ui.log_list_state.selected().unwrap_or(0);
+        let line_in_entry = selected_line.saturating_sub(self.ui.line_of_entry(entry));
+        let line = log_parser::file_line_of_entry(&self.log_entries, self.evicted.first_line(), entry) + line_in_entry;
+
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_string());
+        // The editor may come with arguments of its own, e.g. `code -w`
+        let mut words = editor.split_whitespace();
+        let Some(program) = words.next() else {
+            return Ok(());
+        };
+
+        self.ui.suspend()?;
+        let result = Command::new(program)
+            .args(words)
+            .arg(format!("+{}", line))
+            .arg(&file)
+            .status();
+        self.ui.resume()?;
+        // An editor that fails to start leaves the viewer as it was
+        if let Err(e) = result {
+            self.ui.show_error(format!("failed to start {}: {}", program, e));
+        }
+        Ok(())
+    }
+
+    /// Applies a column layout from the columns prompt and remembers it for this log format.
+    fn set_columns(&mut self, input: &str) -> Result<(), String> {
+        let columns = columns::parse_columns(input)?;
+        self.column_layouts.set(self.log_format, columns.clone());
+        self.ui.columns = columns;
+        let saved = self.column_layouts.save();
+        self.report_save("column layout", saved);
+        Ok(())
+    }
+
+    /// Handles the sort prompt: a column name, optionally followed by `asc` or `desc`.
+    fn sort(&mut self, input: &str) -> Result<(), String> {
+        let mut words = input.split_whitespace();
+        let Some(column) = words.next() else {
+            self.sort_by(None);
+            return Ok(());
+        };
+        let descending = match words.next().map(|w| w.to_ascii_lowercase()).as_deref() {
+            None | Some("asc") => false,
+            Some("desc") => true,
+            Some(other) => return Err(format!("expected asc or desc, found '{}'", other)),
+        };
+        self.sort_by(Some((column.to_string(), descending)));
+        Ok(())
+    }
+
+    /// Changes the row order of the view, keeping the selected entry selected.
+    fn sort_by(&mut self, sort: Option<(String, bool)>) {
+        let selected = self.selected_entry();
+        self.ui.column_sort = sort;
+        self.apply_sort();
+        if self.is_tailing {
+            self.select_last_line();
+        } else if !self.log_entries.is_empty() {
+            self.select_entry(selected);
+        }
+    }
+
+    /// Recomputes the row order for the current sort column, extracting its
+    /// keys only when the column changed.
+    fn apply_sort(&mut self) {
+        self.ui.row_order = match &self.ui.column_sort {
+            Some((column, descending)) => {
+                if self.sort_keys.column() != column {
+                    self.sort_keys = SortKeys::new(&self.log_entries, column);
                 }
+                self.sort_keys.extend(&self.log_entries);
+                self.sort_keys.order(&self.log_entries, *descending)
+            }
+            None => {
+                self.sort_keys = SortKeys::default();
+                Vec::new()
+            }
+        };
+        self.ui.rebuild_line_index(&self.log_entries);
+    }
+
+    /// Selects the last visible line, as tail mode does.
+    fn select_last_line(&mut self) {
+        let total_lines = self.ui.total_lines();
+        self.ui.log_list_state.select(total_lines.checked_sub(1));
+    }
+
+    /// Counts the entry at `index` in the statistics, and in the filtered
+    /// ones unless the filter hides it. Both cover the resident entries.
+    fn count_entry(&mut self, index: usize) {
+        let entry = &self.log_entries[index];
+        self.stats.add(entry);
+        if self.filter.matches(entry) {
+            self.filtered_stats.add(entry);
+        } else {
+            self.ui.hidden_entries.insert(index);
+        }
+    }
+
+    /// Takes an entry counted by `count_entry` back out of the statistics.
+    fn uncount_entry(&mut self, entry: &LogEntry, hidden: bool) {
+        self.stats.remove(entry);
+        if !hidden {
+            self.filtered_stats.remove(entry);
+        }
+    }
+
+    /// Recomputes which entries the filter hides.
+    fn apply_filter(&mut self) {
+        self.ui.hidden_entries.clear();
+        self.filtered_stats.clear();
+        for (i, entry) in self.log_entries.iter().enumerate() {
+            if self.filter.matches(entry) {
+                self.filtered_stats.add(entry);
+            } else {
+                self.ui.hidden_entries.insert(i);
             }
-        })?;
+        }
+        self.ui.rebuild_line_index(&self.log_entries);
+        self.ui.invalidate_timeline();
+    }
 
+    /// Re-applies a changed filter, keeping the selection on the same entry
+    /// or the next one still shown.
+    fn refilter(&mut self) {
+        let selected = self.selected_entry();
+        self.apply_filter();
+        if self.is_tailing {
+            self.select_last_line();
+            return;
+        }
+        let visible = (selected..self.log_entries.len())
+            .chain((0..selected).rev())
+            .find(|i| !self.ui.hidden_entries.contains(i));
+        match visible {
+            Some(entry) => self.select_entry(entry),
+            None => self.ui.log_list_state.select(None),
+        }
+    }
+
+    /// Fields extracted from the selected entry, as listed in the details pane.
+    fn selected_fields(&self) -> Vec<(String, String)> {
+        self.log_entries
+            .get(self.selected_entry())
+            .map(|entry| fields::extract_fields(&entry.message))
+            .unwrap_or_default()
+    }
+
+    fn selected_entry(&self) -> usize {
+        let selected_line = self.ui.log_list_state.selected().unwrap_or(0);
+        self.ui.entry_at_line(selected_line)
+    }
+
+    fn select_entry(&mut self, entry: usize) {
+        let line = self.ui.line_of_entry(entry);
+        self.select_line(line);
+    }
+
+    /// Selects a line of the log view. Tail mode is on exactly when it is
+    /// the last line, however the selection got there.
+    fn select_line(&mut self, line: usize) {
+        let total_lines = self.ui.total_lines();
+        if total_lines == 0 {
+            self.ui.log_list_state.select(None);
+            return;
+        }
+        let line = line.min(total_lines - 1);
+        self.ui.log_list_state.select(Some(line));
+        self.is_tailing = line == total_lines - 1;
+    }
+
+    /// Re-resolves the current file's bookmarks to entry indices.
+    fn refresh_marks(&mut self) {
+        self.entry_marks.clear();
         if let Some(file) = &self.current_file {
-            watcher.watch(file, RecursiveMode::NonRecursive)?;
+            for bookmark in self.bookmarks.for_file(file) {
+                if let Some(index) = bookmark.resolve(&self.log_entries, self.evicted.first_line()) {
+                    self.entry_marks.insert(index, bookmark.mark);
+                }
+            }
         }
+    }
+
+    /// Remembers the file list's width and visibility for the next run.
+    fn save_session(&mut self) {
+        self.session.file_list_width = self.ui.file_list_width;
+        self.session.file_list_hidden = self.ui.file_list_hidden;
+        self.session.auto_expand_errors = self.ui.auto_expand_errors;
+        let saved = self.session.save();
+        self.report_save("session", saved);
+    }
+
+    /// Remembers how the current file's entries are expanded for the next time it is opened.
+    fn save_expansion(&mut self) {
+        let Some(file) = &self.current_file else {
+            return;
+        };
+        self.expansions.set(file, self.ui.expansion.clone());
+        let saved = self.expansions.save();
+        self.report_save("expansion", saved);
+    }
+
+    fn save_bookmarks(&mut self) {
+        let saved = self.bookmarks.save();
+        self.report_save("bookmarks", saved);
+        self.refresh_marks();
+    }
+
+    /// Shows a failure to save state in the status bar; it should not
+    /// interrupt viewing.
+    fn report_save(&mut self, what: &str, saved: io::Result<()>) {
+        if let Err(e) = saved {
+            self.ui.show_error(format!("failed to save {}: {}", what, e));
+        }
+    }
+
+    fn set_mark(&mut self, mark: char) {
+        let Some(file) = self.current_file.clone() else {
+            return;
+        };
+        let entry = self.selected_entry();
+        self.bookmarks.set(&file, mark, &self.log_entries, self.evicted.first_line(), entry);
+        self.ui.show_message(format!("bookmark '{}' set", mark));
+        self.save_bookmarks();
+    }
+
+    /// Jumps to a bookmark by letter, or to the previous/next bookmarked entry for `[`/`]`.
+    fn jump_to_mark(&mut self, mark: char) {
+        let mut target = self.resident_mark(mark);
+        if target.is_none() && mark != ']' {
+            // The bookmark may be on an evicted entry
+            let first_line = self.evicted.first_line();
+            let evicted_line = self.current_file.as_ref().and_then(|file| {
+                self.bookmarks
+                    .for_file(file)
+                    .iter()
+                    .filter(|bookmark| bookmark.line < first_line && (mark == '[' || bookmark.mark == mark))
+                    .map(|bookmark| bookmark.line)
+                    .max()
+            });
+            if let Some(line) = evicted_line {
+                if !self.reload_to_line(line) {
+                    return;
+                }
+                target = self.resident_mark(mark);
+            }
+        }
+        if let Some(entry) = target {
+            self.select_entry(entry);
+        }
+    }
+
+    /// The resident entry a bookmark by letter, or `[`/`]`, refers to.
+    fn resident_mark(&self, mark: char) -> Option<usize> {
+        let current = self.selected_entry();
+        match mark {
+            '[' => self.entry_marks.keys().filter(|&&i| i < current).max().copied(),
+            ']' => self.entry_marks.keys().filter(|&&i| i > current).min().copied(),
+            _ => self.entry_marks.iter().find(|(_, &m)| m == mark).map(|(&i, _)| i),
+        }
+    }
+
+    fn open_bookmark_list(&mut self) {
+        if let Some(file) = &self.current_file {
+            self.ui.bookmark_list = Some(BookmarkList::new(self.bookmarks.for_file(file).to_vec()));
+        }
+    }
 
+    fn handle_bookmark_list(&mut self, event: UIEvent) {
+        let (Some(list), Some(file)) = (self.ui.bookmark_list.as_mut(), self.current_file.clone()) else {
+            return;
+        };
+        match event {
+            UIEvent::Up => list.select_previous(),
+            UIEvent::Down => list.select_next(),
+            UIEvent::Cancel => self.ui.bookmark_list = None,
+            UIEvent::Confirm => {
+                let mark = list.selected_mark();
+                self.ui.bookmark_list = None;
+                if let Some(mark) = mark {
+                    self.is_file_list_focused = false;
+                    self.jump_to_mark(mark);
+                }
+            }
+            UIEvent::Input('d') => {
+                if let Some(mark) = list.selected_mark() {
+                    let selected = list.selected;
+                    self.bookmarks.remove(&file, mark);
+                    self.save_bookmarks();
+                    self.open_bookmark_list();
+                    if let Some(list) = self.ui.bookmark_list.as_mut() {
+                        list.selected = selected.min(list.items.len().saturating_sub(1));
+                    }
+                }
+            }
+            UIEvent::Input('a') => {
+                if let Some(bookmark) = list.items.get(list.selected) {
+                    let prompt = Prompt::with_input(PromptKind::BookmarkNote(bookmark.mark), bookmark.note.clone());
+                    self.ui.bookmark_list = None;
+                    self.ui.prompt = Some(prompt);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_file_finder(&mut self, event: UIEvent) -> io::Result<()> {
+        let Some(finder) = self.ui.file_finder.as_mut() else {
+            return Ok(());
+        };
+        match event {
+            UIEvent::Input(c) => finder.push_char(c, &self.files, &self.directory),
+            UIEvent::Backspace => finder.pop_char(&self.files, &self.directory),
+            UIEvent::Up => finder.select_previous(),
+            UIEvent::Down => finder.select_next(),
+            UIEvent::Confirm => {
+                let selected = finder.selected_file();
+                self.ui.file_finder = None;
+                if let Some(index) = selected {
+                    self.ui.file_list_state.select(Some(index));
+                    let file = self.files[index].clone();
+                    self.open_file(&file);
+                    self.is_file_list_focused = false;
+                }
+            }
+            UIEvent::Cancel => self.ui.file_finder = None,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Takes what the ingest thread has read, for up to `INGEST_BUDGET` so
+    /// input stays responsive. Returns whether anything arrived.
+    fn receive_entries(&mut self) -> bool {
+        let started = Instant::now();
+        let first_new = self.log_entries.len();
+        let mut received = false;
+        while started.elapsed() < INGEST_BUDGET {
+            let Some(message) = self.ingest.as_ref().and_then(Ingest::try_recv) else {
+                break;
+            };
+            received = true;
+            match message {
+                Ingested::Entries(entries) => {
+                    self.resident_bytes += entries.iter().map(retention::entry_size).sum::<u64>();
+                    self.log_entries.extend(entries);
+                }
+                Ingested::Replaced(entry) => {
+                    let Some(last) = self.log_entries.last_mut() else {
+                        continue;
+                    };
+                    let old = mem::replace(last, entry);
+                    self.resident_bytes = self.resident_bytes - retention::entry_size(&old) + retention::entry_size(last);
+                    // Entries that arrived in this call are indexed and counted in full below
+                    let index = self.log_entries.len() - 1;
+                    if index < first_new {
+                        let hidden = self.ui.hidden_entries.remove(&index);
+                        self.uncount_entry(&old, hidden);
+                        self.count_entry(index);
+                        self.ui.last_entry_changed(&self.log_entries);
+                        if let Some((_, descending)) = &self.ui.column_sort {
+                            self.sort_keys.update(&self.log_entries, index);
+                            self.sort_keys.reposition(&self.log_entries, &mut self.ui.row_order, index, *descending);
+                            self.ui.rebuild_line_index(&self.log_entries);
+                        }
+                    }
+                }
+                Ingested::Loaded { size } => {
+                    self.last_file_size = size;
+                    self.refresh_marks();
+                }
+                Ingested::Appended { lines, size } => {
+                    self.ingested.push_back((Instant::now(), lines));
+                    self.last_file_size = size;
+                }
+                Ingested::Truncated => {
+                    // Start over, as `--print` does when following
+                    if let Some(file) = self.current_file.clone() {
+                        self.open_file(&file);
+                        self.ui.show_message(format!("{} was truncated; reloaded", file.display()));
+                    }
+                    return true;
+                }
+                Ingested::Failed(e) => {
+                    let file = self.current_file.as_deref().unwrap_or(&self.directory);
+                    self.ui.show_error(format!("{}: {}", file.display(), e));
+                }
+            }
+        }
+        if self.log_entries.len() > first_new {
+            self.entries_appended(first_new);
+            self.enforce_retention();
+        }
+        received
+    }
+
+    /// Evicts the oldest entries once the source is over its retention
+    /// limits. When scrolled back, what is on screen and the page above it
+    /// stay resident.
+    fn enforce_retention(&mut self) {
+        let mut count = self.retention.excess(&self.log_entries, self.resident_bytes);
+        if !self.is_tailing {
+            let first_shown = self.ui.top_entry().min(self.selected_entry());
+            count = count.min(first_shown.saturating_sub(self.ui.log_view_height));
+        }
+        if count == 0 {
+            return;
+        }
+        let selected = self.selected_entry();
+        let top = self.ui.top_entry();
+        let evicted: Vec<LogEntry> = self.log_entries.drain(..count).collect();
+        for (i, entry) in evicted.iter().enumerate() {
+            let hidden = self.ui.hidden_entries.contains(&i);
+            self.uncount_entry(entry, hidden);
+        }
+        self.resident_bytes -= evicted.iter().map(retention::entry_size).sum::<u64>();
+        self.evicted.add(&evicted);
+        self.ui.shift_entries(count, 0);
+        if self.ui.column_sort.is_some() {
+            self.sort_keys.shift(&self.log_entries, count, 0);
+        }
+        self.entries_moved(selected.saturating_sub(count), top.saturating_sub(count));
+    }
+
+    /// Re-reads the evicted entries just before the resident ones, for
+    /// scrolling back past the first of them. Returns whether any were.
+    fn reload_evicted(&mut self) -> bool {
+        let Some(file) = self.current_file.clone() else {
+            return false;
+        };
+        let (entries, remaining) = match retention::reload(&file, &self.evicted, &self.parser) {
+            Ok(reloaded) => reloaded,
+            Err(e) => {
+                self.ui.show_error(format!("{}: can't re-read evicted entries: {}", file.display(), e));
+                return false;
+            }
+        };
+        let count = entries.len();
+        if count == 0 {
+            return false;
+        }
+        let selected = self.selected_entry();
+        let top = self.ui.top_entry();
+        self.resident_bytes += entries.iter().map(retention::entry_size).sum::<u64>();
+        self.evicted = remaining;
+        self.log_entries.splice(0..0, entries);
+        self.ui.shift_entries(0, count);
+        if let Some((_, descending)) = &self.ui.column_sort {
+            self.sort_keys.shift(&self.log_entries, 0, count);
+            self.sort_keys.merge(&self.log_entries, &mut self.ui.row_order, 0..count, *descending);
+        }
+        for i in 0..count {
+            self.count_entry(i);
+        }
+        self.ui.apply_expansion(&self.log_entries, 0..count);
+        self.entries_moved(selected + count, top + count);
+        true
+    }
+
+    /// Reloads evicted entries until the one at file line `line` is
+    /// resident. Returns false if it can't be.
+    fn reload_to_line(&mut self, line: usize) -> bool {
+        while line < self.evicted.first_line() {
+            if !self.reload_evicted() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Re-indexes after entries were evicted or reloaded at the front, and
+    /// puts the selection and the top of the view back on the same entries.
+    fn entries_moved(&mut self, selected: usize, top: usize) {
+        self.ui.file_start = (self.evicted.first_line(), self.evicted.bytes);
+        self.ui.invalidate_timeline();
+        self.ui.rebuild_line_index(&self.log_entries);
+        self.refresh_marks();
+        if self.is_tailing {
+            self.select_last_line();
+        } else {
+            self.ui.set_top_entry(top);
+            self.select_entry(selected);
+        }
+    }
+
+    /// Filters, sorts and indexes the entries from `first_new` on, all at
+    /// once however many batches they came in.
+    fn entries_appended(&mut self, first_new: usize) {
+        for i in first_new..self.log_entries.len() {
+            self.count_entry(i);
+        }
+        self.ui.apply_expansion(&self.log_entries, first_new..self.log_entries.len());
+        if first_new == 0 {
+            self.detect_format();
+        } else if let Some((_, descending)) = &self.ui.column_sort {
+            self.sort_keys.extend(&self.log_entries);
+            self.sort_keys.merge(&self.log_entries, &mut self.ui.row_order, first_new..self.log_entries.len(), *descending);
+            self.ui.rebuild_line_index(&self.log_entries);
+        } else {
+            self.ui.entries_appended(&self.log_entries);
+        }
+
+        // If we're in tail mode, select the last entry
+        if self.is_tailing {
+            self.select_last_line();
+        }
+    }
+
+    /// Lines appended to the current file per second, averaged over the last few seconds.
+    fn ingest_rate(&mut self) -> f64 {
+        while self.ingested.front().is_some_and(|(at, _)| at.elapsed() > RATE_WINDOW) {
+            self.ingested.pop_front();
+        }
+        let lines: usize = self.ingested.iter().map(|(_, lines)| lines).sum();
+        lines as f64 / RATE_WINDOW.as_secs_f64()
+    }
+
+    fn status_line(&mut self) -> StatusLine {
+        StatusLine {
+            file: self.current_file.clone(),
+            size: self.last_file_size,
+            entry: self.evicted.entries + self.selected_entry(),
+            total: self.evicted.entries + self.log_entries.len(),
+            shown: self.evicted.entries + self.log_entries.len() - self.ui.hidden_entries.len(),
+            tailing: self.is_tailing,
+            filter: self.filter.summary(),
+            format: self.log_format.name(),
+            rate: self.ingest_rate(),
+            queued: self.ingest.as_ref().map_or(0, Ingest::queued),
+            resident: self.retention.is_limited().then_some((self.log_entries.len(), self.resident_bytes)),
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         // Select first file by default if available
         if !self.files.is_empty() {
             self.ui.file_list_state.select(Some(0));
             let file = self.files[0].clone();
-            self.load_log_file(&file)?;
+            self.open_file(&file);
         }
 
+        // Entries arriving in a burst are drawn at most once a frame; input
+        // is drawn straight away
+        let mut last_draw: Option<Instant> = None;
+        let mut entries_changed = false;
         loop {
-            // Check for file updates
-            self.handle_file_update()?;
-            
-            self.ui.draw(&self.files, &self.log_entries, &self.current_file, self.is_file_list_focused)?;
+            entries_changed |= self.receive_entries();
+            let since_draw = last_draw.map_or(REDRAW_INTERVAL, |at| at.elapsed());
+            if self.ui.needs_redraw || (entries_changed && since_draw >= FRAME_INTERVAL) || since_draw >= REDRAW_INTERVAL {
+                let status = self.status_line();
+                let filtered_stats = self.filter.is_active().then_some(&self.filtered_stats);
+                self.ui.draw(&self.files, &self.log_entries, self.is_file_list_focused, (&self.stats, filtered_stats), &self.entry_marks, &status)?;
+                last_draw = Some(Instant::now());
+                entries_changed = false;
+            }
 
-            if let Some(event) = self.ui.handle_events()? {
+            // Keep taking entries while the ingest thread is ahead
+            let timeout = if self.ingest.as_ref().is_some_and(|ingest| ingest.queued() > 0) {
+                Duration::ZERO
+            } else if entries_changed {
+                FRAME_INTERVAL.saturating_sub(since_draw)
+            } else {
+                INPUT_POLL
+            };
+            if let Some((event, count)) = self.ui.handle_events(self.is_file_list_focused, timeout)? {
                 match event {
                     UIEvent::Quit => break,
-                    event => self.handle_navigation(event)?,
+                    event if self.ui.file_finder.is_some() => self.handle_file_finder(event)?,
+                    event if self.ui.prompt.is_some() => self.handle_prompt(event)?,
+                    event if self.ui.bookmark_list.is_some() => self.handle_bookmark_list(event),
+                    event if self.ui.query_view.is_some() => self.handle_query_view(event),
+                    event => self.handle_navigation(event, count)?,
                 }
             }
         }
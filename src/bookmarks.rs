@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::log_parser::{self, LogEntry};
+use crate::state;
+
+/// A named mark on a log entry. Entries are identified by the file line
+/// number of their first line, which stays valid while the file only grows;
+/// the first line's text is kept to re-locate the entry if the file changed.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub mark: char,
+    pub line: usize,
+    pub text: String,
+    #[serde(default)]
+    pub note: String,
+}
+
+/// Bookmarks for every file ever viewed, kept in `bookmarks.json`.
+pub struct Bookmarks {
+    by_file: BTreeMap<String, Vec<Bookmark>>,
+}
+
+impl Bookmarks {
+    const FILE: &'static str = "bookmarks.json";
+
+    pub fn load() -> Self {
+        Self { by_file: state::load_json(Self::FILE) }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        state::save_json(Self::FILE, &self.by_file)
+    }
+
+    pub fn for_file(&self, file: &Path) -> &[Bookmark] {
+        self.by_file.get(&state::file_key(file)).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Marks the entry at `index`, replacing any previous bookmark with the
+    /// same letter. `entries` start at file line `first_line`.
+    pub fn set(&mut self, file: &Path, mark: char, entries: &[LogEntry], first_line: usize, index: usize) {
+        let bookmarks = self.by_file.entry(state::file_key(file)).or_default();
+        let note = bookmarks
+            .iter()
+            .find(|b| b.mark == mark)
+            .map(|b| b.note.clone())
+            .unwrap_or_default();
+        bookmarks.retain(|b| b.mark != mark);
+        bookmarks.push(Bookmark {
+            mark,
+            line: log_parser::file_line_of_entry(entries, first_line, index),
+            text: entries[index].lines[0].clone(),
+            note,
+        });
+        bookmarks.sort_by_key(|b| b.mark);
+    }
+
+    pub fn set_note(&mut self, file: &Path, mark: char, note: &str) {
+        if let Some(bookmark) = self.by_file
+            .get_mut(&state::file_key(file))
+            .and_then(|bookmarks| bookmarks.iter_mut().find(|b| b.mark == mark))
+        {
+            bookmark.note = note.trim().to_string();
+        }
+    }
+
+    pub fn remove(&mut self, file: &Path, mark: char) {
+        let key = state::file_key(file);
+        if let Some(bookmarks) = self.by_file.get_mut(&key) {
+            bookmarks.retain(|b| b.mark != mark);
+            if bookmarks.is_empty() {
+                self.by_file.remove(&key);
+            }
+        }
+    }
+}
+
+impl Bookmark {
+    /// Finds the entry this bookmark points at. If the stored line no longer
+    /// starts with the bookmarked text (e.g. the file was rotated), falls back
+    /// to the entry with the same first line closest to the old position.
+    pub fn resolve(&self, entries: &[LogEntry], first_line: usize) -> Option<usize> {
+        let index = log_parser::entry_at_file_line(entries, first_line, self.line);
+        let matches = |i: usize| entries.get(i).is_some_and(|e| e.lines[0] == self.text);
+        if matches(index) && log_parser::file_line_of_entry(entries, first_line, index) == self.line {
+            return Some(index);
+        }
+        (0..entries.len())
+            .filter(|&i| matches(i))
+            .min_by_key(|&i| i.abs_diff(index))
+    }
+}
+
+/// State of the bookmark list popup.
+pub struct BookmarkList {
+    pub items: Vec<Bookmark>,
+    pub selected: usize,
+}
+
+impl BookmarkList {
+    pub fn new(items: Vec<Bookmark>) -> Self {
+        Self { items, selected: 0 }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.items.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected_mark(&self) -> Option<char> {
+        self.items.get(self.selected).map(|b| b.mark)
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+use regex::Regex;
+use crate::filter::EntryFilter;
+use crate::log_parser::{self, LogLevel};
+
+pub const USAGE: &str = "\
+Usage: multail [OPTIONS] [DIRECTORY]
+       multail --print [OPTIONS] [FILES...]
+
+Options:
+    -P, --print            Print matching entries to stdout instead of opening the viewer
+    -q, --query SQL        Run a query over the entries and print the result table (implies --print)
+    -f, --follow           Keep printing entries as the files grow (with --print)
+    -n, --lines NUM        Only print the last NUM entries of each file (with --print)
+    -p, --pattern PATTERN  Only show entries with a line matching the regex
+    -l, --level LEVEL      Only show entries at or above LEVEL: debug, info, warn, error
+        --since TIME       Only show entries at or after TIME, e.g. 2024-05-01 14:00:00
+        --until TIME       Only show entries at or before TIME
+        --color WHEN       Colour output: auto, always or never (default auto)
+    -h, --help             Show help message
+    -V, --version          Show version information";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+pub struct Args {
+    pub print: bool,
+    pub query: Option<String>,
+    pub follow: bool,
+    pub lines: Option<usize>,
+    pub filter: EntryFilter,
+    pub color: ColorChoice,
+    pub paths: Vec<PathBuf>,
+    pub help: bool,
+    pub version: bool,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        print: false,
+        query: None,
+        follow: false,
+        lines: None,
+        filter: EntryFilter::default(),
+        color: ColorChoice::Auto,
+        paths: Vec::new(),
+        help: false,
+        version: false,
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // Support both `--option value` and `--option=value`
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("missing value for {}", name))
+        };
+
+        match name.as_str() {
+            "-P" | "--print" => parsed.print = true,
+            "-q" | "--query" => {
+                parsed.query = Some(value()?);
+                parsed.print = true;
+            }
+            "-f" | "--follow" => parsed.follow = true,
+            "-n" | "--lines" => {
+                let value = value()?;
+                parsed.lines = Some(value.parse().map_err(|_| format!("invalid number of lines '{}'", value))?);
+            }
+            "-p" | "--pattern" => {
+                let value = value()?;
+                parsed.filter.pattern = Some(Regex::new(&value).map_err(|e| format!("invalid pattern: {}", e))?);
+            }
+            "-l" | "--level" => {
+                let value = value()?;
+                parsed.filter.min_level = Some(
+                    LogLevel::from_str(&value.to_ascii_uppercase())
+                        .ok_or_else(|| format!("invalid level '{}'", value))?,
+                );
+            }
+            "--since" => parsed.filter.since = Some(parse_time(&value()?)?),
+            "--until" => parsed.filter.until = Some(parse_time(&value()?)?),
+            "--color" | "--colour" => {
+                parsed.color = match value()?.as_str() {
+                    "auto" => ColorChoice::Auto,
+                    "always" => ColorChoice::Always,
+                    "never" => ColorChoice::Never,
+                    other => return Err(format!("invalid colour choice '{}'", other)),
+                };
+            }
+            "-h" | "--help" => parsed.help = true,
+            "-V" | "--version" => parsed.version = true,
+            _ if name.starts_with('-') && name.len() > 1 => return Err(format!("unknown option '{}'", name)),
+            _ => parsed.paths.push(PathBuf::from(arg)),
+        }
+    }
+    Ok(parsed)
+}
+
+fn parse_time(value: &str) -> Result<chrono::NaiveDateTime, String> {
+    log_parser::parse_timestamp(value).ok_or_else(|| format!("invalid time '{}'", value))
+}
@@ -0,0 +1,327 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::io;
+use std::mem;
+use std::ops::Range;
+use serde::{Deserialize, Serialize};
+use crate::fields;
+use crate::log_parser::LogEntry;
+use crate::state;
+
+/// How the messages of a file are structured, which decides its column layout.
+#[derive(Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Json,
+    Logfmt,
+    Text,
+}
+
+impl LogFormat {
+    /// Guesses the format from the first entries of a file.
+    pub fn detect(entries: &[LogEntry]) -> Self {
+        let sample = &entries[..entries.len().min(50)];
+        let json = sample.iter().filter(|e| fields::json_object(&e.message).is_some()).count();
+        let logfmt = sample.iter().filter(|e| !fields::extract_fields(&e.message).is_empty()).count();
+        if json * 2 > sample.len() {
+            LogFormat::Json
+        } else if logfmt * 2 > sample.len() {
+            LogFormat::Logfmt
+        } else {
+            LogFormat::Text
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LogFormat::Json => "json",
+            LogFormat::Logfmt => "logfmt",
+            LogFormat::Text => "text",
+        }
+    }
+}
+
+/// A column of the column view: `time`, `level`, `msg` or an extracted
+/// field, with a fixed width or `None` to size it to its content.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Column {
+    pub name: String,
+    pub width: Option<u16>,
+}
+
+/// Parses a column layout like `time level user:12 msg`.
+pub fn parse_columns(spec: &str) -> Result<Vec<Column>, String> {
+    let columns = spec
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.split_once(':') {
+            Some((name, width)) => match width.parse::<u16>() {
+                Ok(width) if width > 0 => Ok(Column { name: name.to_string(), width: Some(width) }),
+                _ => Err(format!("invalid width '{}' for column {}", width, name)),
+            },
+            None => Ok(Column { name: s.to_string(), width: None }),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if columns.is_empty() {
+        return Err("no columns given".to_string());
+    }
+    Ok(columns)
+}
+
+pub fn format_columns(columns: &[Column]) -> String {
+    columns
+        .iter()
+        .map(|c| match c.width {
+            Some(width) => format!("{}:{}", c.name, width),
+            None => c.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Default layout: time and level, the fields of the first structured
+/// entry (up to four), then the message text.
+pub fn default_columns(format: LogFormat, entries: &[LogEntry]) -> Vec<Column> {
+    let mut names = vec!["time".to_string(), "level".to_string()];
+    if format != LogFormat::Text {
+        let first_fields = entries
+            .iter()
+            .take(50)
+            .map(|e| fields::extract_fields(&e.message))
+            .find(|f| !f.is_empty())
+            .unwrap_or_default();
+        names.extend(
+            first_fields
+                .into_iter()
+                .map(|(key, _)| key)
+                .filter(|key| key != "msg" && key != "message")
+                .take(4),
+        );
+    }
+    names.push("msg".to_string());
+    names.into_iter().map(|name| Column { name, width: None }).collect()
+}
+
+/// The text of a column for an entry, given the entry's extracted fields.
+pub fn cell(entry: &LogEntry, fields: &[(String, String)], column: &str) -> String {
+    match column {
+        "time" | "timestamp" => entry.timestamp.clone(),
+        "level" => entry.level.name().to_string(),
+        "msg" | "message" => fields::message_text(&entry.message),
+        name => fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default(),
+    }
+}
+
+/// Fits text into `width` characters, marking cut-off text with `…`.
+pub fn fit(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    if len <= width {
+        format!("{:<width$}", text, width = width)
+    } else if width == 0 {
+        String::new()
+    } else {
+        let mut cut: String = text.chars().take(width - 1).collect();
+        cut.push('…');
+        cut
+    }
+}
+
+/// A column's value for one entry: its text, and the number when it is one.
+struct Key {
+    text: String,
+    number: Option<f64>,
+}
+
+/// The sort keys of the entries for one column, extracted once per entry
+/// so entries appended while tailing are merged into the row order rather
+/// than the whole file being extracted and sorted again.
+#[derive(Default)]
+pub struct SortKeys {
+    column: String,
+    keys: Vec<Key>,
+}
+
+impl SortKeys {
+    pub fn new(entries: &[LogEntry], column: &str) -> Self {
+        let mut keys = Self { column: column.to_string(), keys: Vec::new() };
+        keys.extend(entries);
+        keys
+    }
+
+    pub fn column(&self) -> &str {
+        &self.column
+    }
+
+    fn key(&self, entry: &LogEntry) -> Key {
+        let text = match self.column.as_str() {
+            // Compared on the entries themselves
+            "time" | "timestamp" | "level" => String::new(),
+            column => cell(entry, &fields::extract_fields(&entry.message), column),
+        };
+        Key { number: fields::numeric(&text), text }
+    }
+
+    /// Keys the entries appended since the last call.
+    pub fn extend(&mut self, entries: &[LogEntry]) {
+        for entry in &entries[self.keys.len()..] {
+            let key = self.key(entry);
+            self.keys.push(key);
+        }
+    }
+
+    /// Follows `removed` entries being evicted from the front and `added`
+    /// ones being reloaded there.
+    pub fn shift(&mut self, entries: &[LogEntry], removed: usize, added: usize) {
+        self.keys.drain(..removed.min(self.keys.len()));
+        let reloaded: Vec<Key> = entries[..added].iter().map(|entry| self.key(entry)).collect();
+        self.keys.splice(0..0, reloaded);
+    }
+
+    /// Re-keys an entry that was replaced.
+    pub fn update(&mut self, entries: &[LogEntry], index: usize) {
+        self.keys[index] = self.key(&entries[index]);
+    }
+
+    /// Orders by the column, numbers before text as `fields::compare_values`
+    /// does; equal values keep their file order.
+    fn compare(&self, entries: &[LogEntry], a: usize, b: usize, descending: bool) -> Ordering {
+        let ordering = match self.column.as_str() {
+            "time" | "timestamp" => entries[a].time.cmp(&entries[b].time),
+            "level" => entries[a].level.cmp(&entries[b].level),
+            _ => {
+                let (a, b) = (&self.keys[a], &self.keys[b]);
+                fields::compare_values(a.number, &a.text, b.number, &b.text)
+            }
+        };
+        let ordering = if descending { ordering.reverse() } else { ordering };
+        ordering.then(a.cmp(&b))
+    }
+
+    /// Entry indices in sorted order.
+    pub fn order(&self, entries: &[LogEntry], descending: bool) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..entries.len()).collect();
+        order.sort_by(|&a, &b| self.compare(entries, a, b, descending));
+        order
+    }
+
+    /// Merges the entries in `new`, which `order` doesn't hold yet, into it.
+    pub fn merge(&self, entries: &[LogEntry], order: &mut Vec<usize>, new: Range<usize>, descending: bool) {
+        let mut new: Vec<usize> = new.collect();
+        new.sort_by(|&a, &b| self.compare(entries, a, b, descending));
+        let old = mem::take(order);
+        order.reserve(old.len() + new.len());
+        let (mut old, mut new) = (old.into_iter().peekable(), new.into_iter().peekable());
+        while let (Some(&a), Some(&b)) = (old.peek(), new.peek()) {
+            if self.compare(entries, a, b, descending) == Ordering::Greater {
+                order.push(b);
+                new.next();
+            } else {
+                order.push(a);
+                old.next();
+            }
+        }
+        order.extend(old.chain(new));
+    }
+
+    /// Moves an entry whose key changed to its new place in `order`.
+    pub fn reposition(&self, entries: &[LogEntry], order: &mut Vec<usize>, index: usize, descending: bool) {
+        order.retain(|&i| i != index);
+        let position = order.partition_point(|&i| self.compare(entries, i, index, descending) == Ordering::Less);
+        order.insert(position, index);
+    }
+}
+
+/// Column layouts chosen per log format, kept in `columns.json`.
+pub struct ColumnLayouts {
+    by_format: BTreeMap<String, Vec<Column>>,
+}
+
+impl ColumnLayouts {
+    const FILE: &'static str = "columns.json";
+
+    pub fn load() -> Self {
+        Self { by_format: state::load_json(Self::FILE) }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        state::save_json(Self::FILE, &self.by_format)
+    }
+
+    pub fn get(&self, format: LogFormat) -> Option<&Vec<Column>> {
+        self.by_format.get(format.name())
+    }
+
+    pub fn set(&mut self, format: LogFormat, columns: Vec<Column>) {
+        self.by_format.insert(format.name().to_string(), columns);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuation::Continuation;
+    use crate::log_parser::LogParser;
+
+    fn entries(count: usize) -> Vec<LogEntry> {
+        let content: String = (0..count)
+            .map(|i| format!("[2024-05-01 10:00:00] [INFO] request took={} user=u{}\n", (i * 7919) % 13, i % 5))
+            .collect();
+        LogParser::with_continuation(Continuation::default()).parse(content.as_bytes())
+    }
+
+    #[test]
+    fn merging_appended_entries_matches_a_full_sort() {
+        let entries = entries(200);
+        for column in ["took", "user", "level"] {
+            for descending in [false, true] {
+                let mut keys = SortKeys::new(&entries[..50], column);
+                let mut order = keys.order(&entries[..50], descending);
+                for end in [51, 90, 150, 200] {
+                    let first_new = order.len();
+                    keys.extend(&entries[..end]);
+                    keys.merge(&entries[..end], &mut order, first_new..end, descending);
+                }
+                assert_eq!(order, SortKeys::new(&entries, column).order(&entries, descending), "{} {}", column, descending);
+            }
+        }
+    }
+
+    #[test]
+    fn mixed_numbers_and_text_sort_consistently() {
+        let values = ["12", "4a", "5", "nan", "10", "9", "abc", "5a", "-3", "NaN", "1e3"];
+        let content: String = (0..3000)
+            .map(|i| format!("[2024-05-01 10:00:00] [INFO] request v={}\n", values[(i * 7) % values.len()]))
+            .collect();
+        let entries = LogParser::with_continuation(Continuation::default()).parse(content.as_bytes());
+        for descending in [false, true] {
+            let keys = SortKeys::new(&entries, "v");
+            let order = keys.order(&entries, descending);
+            let mut sorted: Vec<String> = order.iter().map(|&i| keys.keys[i].text.clone()).collect();
+            sorted.dedup();
+            let mut expected = vec!["-3", "5", "9", "10", "12", "1e3", "4a", "5a", "NaN", "abc", "nan"];
+            if descending {
+                expected.reverse();
+            }
+            assert_eq!(sorted, expected);
+
+            let mut merged = keys.order(&entries[..1000], descending);
+            keys.merge(&entries, &mut merged, 1000..entries.len(), descending);
+            assert_eq!(merged, order);
+        }
+    }
+
+    #[test]
+    fn replaced_entry_moves_to_its_new_place() {
+        let mut entries = entries(30);
+        let mut keys = SortKeys::new(&entries, "took");
+        let mut order = keys.order(&entries, false);
+        entries[29].message = "request took=100 user=u0".to_string();
+        keys.update(&entries, 29);
+        keys.reposition(&entries, &mut order, 29, false);
+        assert_eq!(order, SortKeys::new(&entries, "took").order(&entries, false));
+        assert_eq!(order.last(), Some(&29));
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use serde::Deserialize;
+
+/// Settings from `~/.config/multail/config.toml`. Missing sections and
+/// keys keep their defaults.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub display: DisplayConfig,
+    pub keys: KeysConfig,
+    pub retention: RetentionConfig,
+    pub multiline: MultilineConfig,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    pub wrap_lines: bool,
+    pub show_line_numbers: bool,
+    /// Width of the file list in columns, until it is resized in the viewer.
+    pub file_list_width: Option<u16>,
+    pub file_list_min_width: Option<u16>,
+    pub file_list_max_width: Option<u16>,
+}
+
+/// The `[keys]` section: a preset (`default`, `vim` or `less`) and, per
+/// context, key chords mapped to action names, e.g. `"Ctrl-d" = "half_page_down"`.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct KeysConfig {
+    pub preset: Option<String>,
+    pub file_list: BTreeMap<String, String>,
+    pub log_view: BTreeMap<String, String>,
+    pub details: BTreeMap<String, String>,
+    pub global: BTreeMap<String, String>,
+    pub popup: BTreeMap<String, String>,
+}
+
+/// The `[retention]` section: how many entries, or MiB of them, to keep in
+/// memory per source. Older entries past a limit are dropped and re-read
+/// from the file when scrolled back to. Unlimited by default.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    pub max_entries: Option<usize>,
+    pub max_mib: Option<u64>,
+    /// Limits for particular files, by file name, e.g. `"debug.log" = { max_mib = 512 }`.
+    pub files: BTreeMap<String, RetentionLimits>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RetentionLimits {
+    pub max_entries: Option<usize>,
+    pub max_mib: Option<u64>,
+}
+
+/// The `[multiline]` section: which lines without a header of their own
+/// continue the entry above them.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct MultilineConfig {
+    /// Built-in rules to use, by name; all of them when unset.
+    pub rules: Option<Vec<String>>,
+    /// Extra regular expressions for continuation lines.
+    pub patterns: Vec<String>,
+    /// Lines an entry grows to before further lines start a new one.
+    pub max_lines: Option<usize>,
+    /// How long the last entry of a tailed file waits for more lines, in milliseconds.
+    pub timeout_ms: Option<u64>,
+}
+
+impl Config {
+    /// Reads the config file, if there is one.
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = config_path() else {
+            return Ok(Self::default());
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("multail").join("config.toml"))
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+use regex::RegexSet;
+use crate::config::MultilineConfig;
+
+/// Built-in rules for lines that continue the entry above them, by name.
+const RULES: &[(&str, &[&str])] = &[
+    // Indented lines and blank lines, as in most stack traces and dumps
+    ("indented", &[r"^(\s|$)"]),
+    ("caused_by", &[r"^Caused by: ", r"^Suppressed: "]),
+    ("traceback", &[
+        r"^Traceback \(most recent call last\):",
+        r"^During handling of the above exception",
+        r"^The above exception was the direct cause",
+    ]),
+    ("at", &[r"^\s*at "]),
+    // The exception line itself: `java.lang.IllegalStateException: ...`, `ValueError: ...`
+    ("exception", &[r"^[\w$]+(\.[\w$]+)*(Exception|Error|Throwable)(: |$)"]),
+    ("go_panic", &[
+        r"^panic: ",
+        r"^fatal error: ",
+        r"^\[signal ",
+        r"^goroutine \d+ \[",
+        r"^created by ",
+        r"^exit status \d+$",
+        // Frames such as `main.main()` or `main.(*Server).Run(0xc000010000)`
+        r"^[\w./*()-]+\([^)]*\)$",
+    ]),
+    ("rust_backtrace", &[
+        r"^thread '.*' panicked at ",
+        r"^stack backtrace:$",
+        r"^note: run with `RUST_BACKTRACE",
+        r"^note: Some details are omitted",
+    ]),
+];
+
+/// Lines an entry grows to by default before further lines start a new one.
+const DEFAULT_MAX_LINES: usize = 1000;
+
+/// How long a tailed entry waits for more lines by default.
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Which lines without a header of their own belong to the entry above:
+/// those matching the `[multiline]` rules, up to `max_lines` per entry.
+#[derive(Clone)]
+pub struct Continuation {
+    patterns: RegexSet,
+    max_lines: usize,
+    /// How long the last entry of a tailed file waits for more lines
+    /// before it is shown.
+    pub timeout: Duration,
+}
+
+impl Default for Continuation {
+    fn default() -> Self {
+        let patterns = RULES.iter().flat_map(|(_, patterns)| patterns.iter());
+        Self {
+            patterns: RegexSet::new(patterns).expect("built-in continuation rules are valid"),
+            max_lines: DEFAULT_MAX_LINES,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl Continuation {
+    /// Builds the rules from the `[multiline]` config: the named built-in
+    /// rules (all of them by default) and any extra patterns.
+    pub fn from_config(config: &MultilineConfig) -> Result<Self, String> {
+        let mut patterns: Vec<String> = Vec::new();
+        match &config.rules {
+            None => patterns.extend(RULES.iter().flat_map(|(_, patterns)| patterns.iter().map(|p| p.to_string()))),
+            Some(names) => {
+                for name in names {
+                    let (_, rule) = RULES.iter().find(|(rule, _)| rule == name).ok_or_else(|| {
+                        let known: Vec<&str> = RULES.iter().map(|(rule, _)| *rule).collect();
+                        format!("unknown rule '{}' (expected one of {})", name, known.join(", "))
+                    })?;
+                    patterns.extend(rule.iter().map(|p| p.to_string()));
+                }
+            }
+        }
+        patterns.extend(config.patterns.iter().cloned());
+        let patterns = RegexSet::new(&patterns).map_err(|e| format!("invalid pattern: {}", e))?;
+        Ok(Self {
+            patterns,
+            max_lines: config.max_lines.unwrap_or(DEFAULT_MAX_LINES).max(1),
+            timeout: config.timeout_ms.map_or(DEFAULT_TIMEOUT, Duration::from_millis),
+        })
+    }
+
+    /// Whether `line` continues an entry that already has `lines` lines.
+    pub fn continues(&self, line: &str, lines: usize) -> bool {
+        lines < self.max_lines && self.patterns.is_match(line)
+    }
+}
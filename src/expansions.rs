@@ -0,0 +1,45 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::state;
+
+/// How a file's multi-line entries were expanded: everything at once, and
+/// the entries toggled by hand against that. Entries are identified by
+/// their first line's text, so expansion survives reloads and rotation.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Expansion {
+    pub all: bool,
+    pub toggled: HashSet<String>,
+}
+
+/// Expansion for every file ever viewed, kept in `expansions.json`.
+pub struct Expansions {
+    by_file: BTreeMap<String, Expansion>,
+}
+
+impl Expansions {
+    const FILE: &'static str = "expansions.json";
+
+    pub fn load() -> Self {
+        Self { by_file: state::load_json(Self::FILE) }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        state::save_json(Self::FILE, &self.by_file)
+    }
+
+    pub fn for_file(&self, file: &Path) -> Expansion {
+        self.by_file.get(&state::file_key(file)).cloned().unwrap_or_default()
+    }
+
+    /// Stores a file's expansion; one with nothing expanded is forgotten.
+    pub fn set(&mut self, file: &Path, expansion: Expansion) {
+        if !expansion.all && expansion.toggled.is_empty() {
+            self.by_file.remove(&state::file_key(file));
+        } else {
+            self.by_file.insert(state::file_key(file), expansion);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use serde_json::json;
+use crate::log_parser::{LogEntry, LogLevel};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Raw,
+    JsonLines,
+    Csv,
+    Html,
+}
+
+impl ExportFormat {
+    /// Picks the format from the output file extension, defaulting to raw text.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+            Some("json" | "jsonl" | "ndjson") => ExportFormat::JsonLines,
+            Some("csv") => ExportFormat::Csv,
+            Some("html" | "htm") => ExportFormat::Html,
+            _ => ExportFormat::Raw,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Raw => "text",
+            ExportFormat::JsonLines => "JSON lines",
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Html => "HTML",
+        }
+    }
+}
+
+pub fn export(entries: &[&LogEntry], path: &Path, format: ExportFormat) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    match format {
+        ExportFormat::Raw => {
+            for entry in entries {
+                for line in &entry.lines {
+                    writeln!(out, "{}", line)?;
+                }
+            }
+        }
+        ExportFormat::JsonLines => {
+            for entry in entries {
+                let value = json!({
+                    "timestamp": entry.timestamp,
+                    "time": entry.time.map(|t| t.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+                    "level": entry.level.name(),
+                    "message": entry.message,
+                    "lines": entry.lines,
+                });
+                writeln!(out, "{}", value)?;
+            }
+        }
+        ExportFormat::Csv => {
+            writeln!(out, "timestamp,level,message,text")?;
+            for entry in entries {
+                writeln!(
+                    out,
+                    "{},{},{},{}",
+                    csv_field(&entry.timestamp),
+                    entry.level.name(),
+                    csv_field(&entry.message),
+                    csv_field(&entry.lines.join("\n")),
+                )?;
+            }
+        }
+        ExportFormat::Html => {
+            writeln!(out, "<!DOCTYPE html>")?;
+            writeln!(out, "<html><head><meta charset=\"utf-8\"><title>multail export</title></head>")?;
+            writeln!(out, "<body style=\"background:#1e1e1e;color:#d4d4d4\"><pre>")?;
+            for entry in entries {
+                writeln!(
+                    out,
+                    "<span style=\"color:{}\">{}</span>",
+                    html_color(entry.level),
+                    html_escape(&entry.lines.join("\n")),
+                )?;
+            }
+            writeln!(out, "</pre></body></html>")?;
+        }
+    }
+    out.flush()
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn html_color(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Debug => "#808080",
+        LogLevel::Info => "#d4d4d4",
+        LogLevel::Warn => "#e5c07b",
+        LogLevel::Error => "#e06c75",
+    }
+}
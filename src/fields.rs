@@ -0,0 +1,91 @@
+use std::cmp::Ordering;
+use std::sync::OnceLock;
+use regex::Regex;
+use serde_json::Value;
+
+/// Extracts structured fields from a log message: the keys of an embedded
+/// JSON object (nested keys joined with `.`), or otherwise logfmt-style
+/// `key=value` pairs. Values keep their original order.
+pub fn extract_fields(message: &str) -> Vec<(String, String)> {
+    if let Some(object) = json_object(message) {
+        let mut fields = Vec::new();
+        flatten_json("", &object, &mut fields);
+        return fields;
+    }
+
+    logfmt_regex()
+        .captures_iter(message)
+        .map(|caps| {
+            let value = &caps[2];
+            let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                Some(quoted) => quoted.replace("\\\"", "\"").replace("\\\\", "\\"),
+                None => value.to_string(),
+            };
+            (caps[1].to_string(), value)
+        })
+        .collect()
+}
+
+/// A value's number for sorting and comparing, if it is one. `NaN` counts
+/// as text, having no place among the numbers.
+pub fn numeric(text: &str) -> Option<f64> {
+    text.trim().parse::<f64>().ok().filter(|n| !n.is_nan())
+}
+
+/// Orders values given as their number (from `numeric`) and text: numbers
+/// by value before all text, and text lexically. Comparing as numbers only
+/// when both are would not be a total order: "10" < "5a" < "9" < "10".
+pub fn compare_values(a: Option<f64>, a_text: &str, b: Option<f64>, b_text: &str) -> Ordering {
+    match (a, b) {
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a_text.cmp(b_text),
+    }
+}
+
+/// Returns the free text of a message: whatever precedes its JSON object
+/// or first `key=value` pair, falling back to a `msg` or `message` field.
+pub fn message_text(message: &str) -> String {
+    let end = match json_object(message) {
+        Some(_) => message.find('{').unwrap_or(message.len()),
+        None => logfmt_regex().find(message).map_or(message.len(), |m| m.start()),
+    };
+    let text = message[..end].trim();
+    if !text.is_empty() {
+        return text.to_string();
+    }
+    extract_fields(message)
+        .into_iter()
+        .find(|(key, _)| key == "msg" || key == "message")
+        .map(|(_, value)| value)
+        .unwrap_or_default()
+}
+
+/// Returns the JSON object embedded in a message, if any, for pretty-printing.
+pub fn json_object(message: &str) -> Option<Value> {
+    let start = message.find('{')?;
+    match serde_json::from_str::<Value>(message[start..].trim_end()) {
+        Ok(value @ Value::Object(_)) => Some(value),
+        _ => None,
+    }
+}
+
+fn logfmt_regex() -> &'static Regex {
+    static LOGFMT: OnceLock<Regex> = OnceLock::new();
+    LOGFMT.get_or_init(|| Regex::new(r#"(?:^|\s)([A-Za-z_][\w.\-]*)=("(?:[^"\\]|\\.)*"|\S*)"#).unwrap())
+}
+
+fn flatten_json(prefix: &str, value: &Value, fields: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(object) if prefix.is_empty() || !object.is_empty() => {
+            for (key, value) in object {
+                let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten_json(&key, value, fields);
+            }
+        }
+        Value::String(s) => fields.push((prefix.to_string(), s.clone())),
+        Value::Null => fields.push((prefix.to_string(), String::new())),
+        other => fields.push((prefix.to_string(), other.to_string())),
+    }
+}
@@ -0,0 +1,72 @@
+use chrono::NaiveDateTime;
+use regex::Regex;
+use crate::fields;
+use crate::log_parser::{LogEntry, LogLevel};
+
+/// Criteria an entry must satisfy to be shown: a minimum level, a regex
+/// matched against any of its lines, a time range and exact values of
+/// extracted fields.
+#[derive(Default, Clone)]
+pub struct EntryFilter {
+    pub min_level: Option<LogLevel>,
+    pub pattern: Option<Regex>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+    pub fields: Vec<(String, String)>,
+}
+
+impl EntryFilter {
+    pub fn is_active(&self) -> bool {
+        self.min_level.is_some()
+            || self.pattern.is_some()
+            || self.since.is_some()
+            || self.until.is_some()
+            || !self.fields.is_empty()
+    }
+
+    /// A short description of the active criteria, for the status bar.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(level) = self.min_level {
+            parts.push(format!("level≥{}", level.name()));
+        }
+        if let Some(pattern) = &self.pattern {
+            parts.push(format!("/{}/", pattern.as_str()));
+        }
+        if let Some(since) = self.since {
+            parts.push(format!("since {}", since.format("%Y-%m-%d %H:%M:%S")));
+        }
+        if let Some(until) = self.until {
+            parts.push(format!("until {}", until.format("%Y-%m-%d %H:%M:%S")));
+        }
+        parts.extend(self.fields.iter().map(|(key, value)| format!("{}={}", key, value)));
+        parts.join(" ")
+    }
+
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        if self.min_level.is_some_and(|min| entry.level < min) {
+            return false;
+        }
+        if let Some(pattern) = &self.pattern {
+            if !entry.lines.iter().any(|line| pattern.is_match(line)) {
+                return false;
+            }
+        }
+        if self.since.is_some() || self.until.is_some() {
+            // Entries without a timestamp can't be placed in a time range
+            let Some(time) = entry.time else {
+                return false;
+            };
+            if self.since.is_some_and(|since| time < since) || self.until.is_some_and(|until| time > until) {
+                return false;
+            }
+        }
+        if !self.fields.is_empty() {
+            let extracted = fields::extract_fields(&entry.message);
+            if !self.fields.iter().all(|field| extracted.contains(field)) {
+                return false;
+            }
+        }
+        true
+    }
+}
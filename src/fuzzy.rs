@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+
+/// State of the Ctrl-P file finder popup: the typed query and the ranked
+/// list of matching files (as indices into `LogViewer.files`).
+pub struct FileFinder {
+    pub query: String,
+    pub matches: Vec<FinderMatch>,
+    pub selected: usize,
+}
+
+pub struct FinderMatch {
+    pub file_index: usize,
+    pub display: String,
+    pub positions: Vec<usize>,
+}
+
+impl FileFinder {
+    pub fn new(files: &[PathBuf], root: &Path) -> Self {
+        let mut finder = Self {
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+        };
+        finder.update(files, root);
+        finder
+    }
+
+    pub fn push_char(&mut self, c: char, files: &[PathBuf], root: &Path) {
+        self.query.push(c);
+        self.update(files, root);
+    }
+
+    pub fn pop_char(&mut self, files: &[PathBuf], root: &Path) {
+        self.query.pop();
+        self.update(files, root);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected_file(&self) -> Option<usize> {
+        self.matches.get(self.selected).map(|m| m.file_index)
+    }
+
+    fn update(&mut self, files: &[PathBuf], root: &Path) {
+        let mut scored: Vec<(i64, FinderMatch)> = files
+            .iter()
+            .enumerate()
+            .filter_map(|(i, file)| {
+                let display = relative_path(file, root);
+                let (score, positions) = fuzzy_match(&self.query, &display)?;
+                Some((score, FinderMatch { file_index: i, display, positions }))
+            })
+            .collect();
+
+        // Best score first; shorter paths win ties, then alphabetical order
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            b_score
+                .cmp(a_score)
+                .then(a.display.len().cmp(&b.display.len()))
+                .then(a.display.cmp(&b.display))
+        });
+
+        self.matches = scored.into_iter().map(|(_, m)| m).collect();
+        self.selected = 0;
+    }
+}
+
+pub fn relative_path(file: &Path, root: &Path) -> String {
+    file.strip_prefix(root)
+        .unwrap_or(file)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Matches `query` as a case-insensitive subsequence of `candidate`.
+/// Returns the score and the char positions of the matched characters,
+/// or `None` if not every query character could be found in order.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let candidate_chars: Vec<char> = candidate.chars().collect();
+    let mut positions = Vec::with_capacity(query.len());
+    let mut score = 0i64;
+    let mut next = 0;
+
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let q = q.to_ascii_lowercase();
+        let offset = candidate_chars[next..]
+            .iter()
+            .position(|c| c.to_ascii_lowercase() == q)?;
+        let pos = next + offset;
+
+        score += 10;
+        // Consecutive matches and matches at word boundaries rank higher
+        if positions.last().is_some_and(|&last| last + 1 == pos) {
+            score += 15;
+        }
+        if pos == 0 || matches!(candidate_chars[pos - 1], '/' | '\\' | '_' | '-' | '.' | ' ') {
+            score += 10;
+        }
+        // Penalise gaps between matched characters
+        score -= offset as i64;
+
+        positions.push(pos);
+        next = pos + 1;
+    }
+
+    // Prefer matches in the file name over matches in parent directories
+    let file_name_start = candidate.rfind(['/', '\\']).map_or(0, |i| i + 1);
+    let file_name_start = candidate[..file_name_start].chars().count();
+    score += positions.iter().filter(|&&p| p >= file_name_start).count() as i64 * 2;
+
+    Some((score, positions))
+}
@@ -0,0 +1,126 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use crate::log_parser::{self, LogEntry};
+
+/// A parsed goto prompt input.
+#[derive(Debug, PartialEq)]
+pub enum GotoTarget {
+    /// A 1-based line number in the file
+    Line(usize),
+    /// An absolute time; a bare time of day is resolved against the date of the current entry
+    Time(NaiveDateTime),
+    TimeOfDay(NaiveTime),
+    /// An offset from the timestamp of the current entry, e.g. `-5m` or `+1h30m`
+    Relative(Duration),
+}
+
+pub fn parse_goto(input: &str) -> Result<GotoTarget, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("empty input".to_string());
+    }
+
+    if let Ok(line) = input.parse::<usize>() {
+        return Ok(GotoTarget::Line(line));
+    }
+
+    if let Some(rest) = input.strip_prefix(['+', '-']) {
+        let duration = parse_duration(rest).ok_or_else(|| format!("invalid offset '{}'", input))?;
+        return Ok(GotoTarget::Relative(if input.starts_with('-') { -duration } else { duration }));
+    }
+
+    if let Some(time) = log_parser::parse_timestamp(input) {
+        return Ok(GotoTarget::Time(time));
+    }
+
+    ["%H:%M:%S%.f", "%H:%M"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(input, format).ok())
+        .map(GotoTarget::TimeOfDay)
+        .ok_or_else(|| format!("expected a line number, time or offset, got '{}'", input))
+}
+
+/// Parses durations such as `30s`, `5m`, `1h30m` or `2d`.
+fn parse_duration(s: &str) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let n: i64 = number.parse().ok()?;
+        number.clear();
+        total += match c {
+            's' => Duration::seconds(n),
+            'm' => Duration::minutes(n),
+            'h' => Duration::hours(n),
+            'd' => Duration::days(n),
+            _ => return None,
+        };
+    }
+    // A bare number means seconds
+    if !number.is_empty() {
+        total += Duration::seconds(number.parse().ok()?);
+    }
+    if s.is_empty() { None } else { Some(total) }
+}
+
+/// Returns the timestamp of an entry, falling back to the closest earlier
+/// timestamped entry for continuation lines and unparsed entries.
+pub fn time_at(entries: &[LogEntry], index: usize) -> Option<NaiveDateTime> {
+    entries[..=index.min(entries.len().checked_sub(1)?)]
+        .iter()
+        .rev()
+        .find_map(|e| e.time)
+}
+
+/// Resolves a goto target to an entry index, relative to the entry at
+/// `current`. `entries` start at file line `first_line`.
+pub fn resolve(entries: &[LogEntry], first_line: usize, target: &GotoTarget, current: usize) -> Result<usize, String> {
+    if entries.is_empty() {
+        return Err("no entries loaded".to_string());
+    }
+
+    let time = match target {
+        GotoTarget::Line(line) => return Ok(log_parser::entry_at_file_line(entries, first_line, *line)),
+        GotoTarget::Time(time) => *time,
+        GotoTarget::TimeOfDay(time) => {
+            let date = time_at(entries, current)
+                .or_else(|| entries.iter().find_map(|e| e.time))
+                .map(|t| t.date())
+                .unwrap_or_else(|| NaiveDate::from_ymd_opt(1970, 1, 1).unwrap());
+            date.and_time(*time)
+        }
+        GotoTarget::Relative(offset) => {
+            time_at(entries, current).ok_or("current entry has no timestamp")? + *offset
+        }
+    };
+    nearest_entry(entries, time).ok_or_else(|| "no timestamped entries".to_string())
+}
+
+/// Binary-searches the entries for the one whose timestamp is closest to `time`.
+pub fn nearest_entry(entries: &[LogEntry], time: NaiveDateTime) -> Option<usize> {
+    let first_timed = entries.iter().position(|e| e.time.is_some())?;
+    let (mut low, mut high) = (first_timed, entries.len());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if time_at(entries, mid)? < time {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    // `low` is the first entry at or after `time`; the previous timestamped entry may be closer
+    let after = (low < entries.len()).then(|| (low, time_at(entries, low).unwrap()));
+    let before = entries[first_timed..low]
+        .iter()
+        .rposition(|e| e.time.is_some())
+        .map(|i| (first_timed + i, entries[first_timed + i].time.unwrap()));
+    match (before, after) {
+        (Some((b, bt)), Some((a, at))) => Some(if time - bt < at - time { b } else { a }),
+        (Some((b, _)), None) => Some(b),
+        (None, Some((a, _))) => Some(a),
+        (None, None) => None,
+    }
+}
@@ -0,0 +1,433 @@
+use std::fs;
+use std::mem;
+use std::io::{self, Read, Seek};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::log_parser::{LogEntry, LogParser};
+
+/// Entries sent to the UI in one message.
+const BATCH_ENTRIES: usize = 1000;
+
+/// Bytes the initial read parses at a time, sending what they complete.
+const READ_CHUNK: usize = 1 << 20;
+
+/// Batches the reader gets ahead of the UI before it waits for it to catch up.
+const QUEUE_BATCHES: usize = 64;
+
+/// How often the reader checks the file for appended content.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What the reader thread sends the UI, in file order.
+pub enum Ingested {
+    /// Parsed entries, at most `BATCH_ENTRIES` of them.
+    Entries(Vec<LogEntry>),
+    /// The last entry sent again, read further: with continuation lines or
+    /// the rest of a line that arrived after it was sent.
+    Replaced(LogEntry),
+    /// The initial read is done and found `size` bytes.
+    Loaded { size: u64 },
+    /// Content appended since was read: `lines` lines, bringing the file to `size` bytes.
+    Appended { lines: usize, size: u64 },
+    /// The file shrank below what was read, truncated or rotated in place;
+    /// nothing more will arrive and the file is to be read again.
+    Truncated,
+    /// Reading failed; nothing more will arrive.
+    Failed(String),
+}
+
+/// A thread reading and parsing one source, then following it as it grows.
+/// Its batches queue up in a bounded channel, so a reader far ahead of the
+/// UI waits rather than piling up memory. Dropping this stops the thread.
+pub struct Ingest {
+    receiver: Receiver<Ingested>,
+    queued: Arc<AtomicUsize>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Ingest {
+    /// Starts reading `path` with `parser`. The file is opened here so a
+    /// missing or unreadable file is reported straight away.
+    pub fn start(path: &PathBuf, parser: LogParser) -> io::Result<Self> {
+        let file = fs::File::open(path)?;
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_BATCHES);
+        let queued = Arc::new(AtomicUsize::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        let reader = Reader {
+            path: path.clone(),
+            parser,
+            sender,
+            queued: queued.clone(),
+            stop: stop.clone(),
+        };
+        thread::spawn(move || reader.run(file));
+        Ok(Self { receiver, queued, stop })
+    }
+
+    /// The next message, if one is waiting.
+    pub fn try_recv(&self) -> Option<Ingested> {
+        let message = self.receiver.try_recv().ok()?;
+        if let Ingested::Entries(entries) = &message {
+            self.queued.fetch_sub(entries.len(), Ordering::Relaxed);
+        }
+        Some(message)
+    }
+
+    /// Entries parsed but not yet taken by the UI.
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Ingest {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+struct Reader {
+    path: PathBuf,
+    parser: LogParser,
+    sender: SyncSender<Ingested>,
+    queued: Arc<AtomicUsize>,
+    stop: Arc<AtomicBool>,
+}
+
+/// The end of what has been read so far, which later reads may add to.
+#[derive(Default)]
+struct Tail {
+    /// Bytes after the last newline: a line still being written.
+    partial: Vec<u8>,
+    /// The last entry, which more lines may still continue.
+    open: Option<LogEntry>,
+    /// Whether the UI's last entry is `open`, as it was with `sent_lines`
+    /// lines; to be replaced when it changes.
+    shown: bool,
+    sent_lines: usize,
+    /// A line without a newline the timeout let through as the last line
+    /// of `open`, which is parsed again once more of it arrives.
+    flushed: Vec<u8>,
+}
+
+impl Reader {
+    fn run(self, mut file: fs::File) {
+        // The last entry is held back while more lines may still continue
+        // it, until the group timeout passes without any
+        let mut tail = Tail::default();
+        let mut read_to = 0;
+        let mut chunk = vec![0; READ_CHUNK];
+        loop {
+            if self.stop.load(Ordering::Relaxed) {
+                return;
+            }
+            let read = match file.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.send(Ingested::Failed(e.to_string()));
+                    return;
+                }
+            };
+            read_to += read as u64;
+            if !self.ingest(&mut tail, chunk[..read].to_vec()) {
+                return;
+            }
+        }
+        let mut last_read = Instant::now();
+        if !self.send(Ingested::Loaded { size: read_to }) {
+            return;
+        }
+
+        while !self.stop.load(Ordering::Relaxed) {
+            thread::sleep(POLL_INTERVAL);
+            match self.read_appended(read_to) {
+                Ok(Some(new_content)) => {
+                    read_to += new_content.len() as u64;
+                    let lines = new_content.iter().filter(|&&b| b == b'\n').count();
+                    if !self.ingest(&mut tail, new_content) || !self.send(Ingested::Appended { lines, size: read_to }) {
+                        return;
+                    }
+                    last_read = Instant::now();
+                }
+                Ok(None) if last_read.elapsed() >= self.parser.group_timeout() => {
+                    if !self.flush(&mut tail) {
+                        return;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    self.send(Ingested::Truncated);
+                    return;
+                }
+                Err(e) => {
+                    self.send(Ingested::Failed(e.to_string()));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Reads whatever was appended past `read_to`. A file now shorter than
+    /// that fails with `UnexpectedEof`.
+    fn read_appended(&self, read_to: u64) -> io::Result<Option<Vec<u8>>> {
+        let current_size = fs::metadata(&self.path)?.len();
+        if current_size < read_to {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the file has been truncated"));
+        }
+        if current_size == read_to {
+            return Ok(None);
+        }
+        let mut file = fs::File::open(&self.path)?;
+        file.seek(io::SeekFrom::Start(read_to))?;
+        let mut new_content = Vec::new();
+        file.take(current_size - read_to).read_to_end(&mut new_content)?;
+        Ok(Some(new_content))
+    }
+
+    /// Parses the complete lines of `bytes` after what `tail` holds; a
+    /// trailing partial line waits for the rest of it.
+    fn ingest(&self, tail: &mut Tail, bytes: Vec<u8>) -> bool {
+        if !tail.flushed.is_empty() {
+            // The flushed line goes on in `bytes`: take it back out of the
+            // open entry to parse it again in full
+            if let Some(entry) = &mut tail.open {
+                entry.lines.pop();
+                entry.bytes -= tail.flushed.len() as u64;
+                if entry.lines.is_empty() {
+                    tail.open = None;
+                }
+            }
+            tail.sent_lines = 0;
+            tail.partial = mem::take(&mut tail.flushed);
+        }
+        tail.partial.extend(bytes);
+        let Some(end) = tail.partial.iter().rposition(|&b| b == b'\n') else {
+            return true;
+        };
+        let rest = tail.partial.split_off(end + 1);
+        let complete = mem::replace(&mut tail.partial, rest);
+        self.parse(tail, &complete)
+    }
+
+    /// Parses lines following on from the open entry and sends the entries
+    /// they complete. One the UI already shows replaces it if it changed.
+    /// Returns false once the UI has gone away.
+    fn parse(&self, tail: &mut Tail, content: &[u8]) -> bool {
+        let mut entries = self.parser.parse_continuing(content, &mut tail.open);
+        if tail.shown && !entries.is_empty() {
+            let shown = entries.remove(0);
+            tail.shown = false;
+            if shown.lines.len() != tail.sent_lines && !self.send(Ingested::Replaced(shown)) {
+                return false;
+            }
+        }
+        self.send_entries(entries)
+    }
+
+    /// Sends what the group timeout no longer holds back: a last line
+    /// without a newline, and the open entry as it is now.
+    fn flush(&self, tail: &mut Tail) -> bool {
+        if !tail.partial.is_empty() {
+            let line = mem::take(&mut tail.partial);
+            if !self.parse(tail, &line) {
+                return false;
+            }
+            tail.flushed = line;
+        }
+        let Some(entry) = &tail.open else {
+            return true;
+        };
+        let sent = if !tail.shown {
+            self.send_entries(vec![entry.clone()])
+        } else if entry.lines.len() != tail.sent_lines {
+            self.send(Ingested::Replaced(entry.clone()))
+        } else {
+            true
+        };
+        tail.shown = true;
+        tail.sent_lines = entry.lines.len();
+        sent
+    }
+
+    /// Sends entries in batches. Returns false once the UI has gone away.
+    fn send_entries(&self, entries: Vec<LogEntry>) -> bool {
+        let mut entries = entries.into_iter().peekable();
+        while entries.peek().is_some() {
+            let batch: Vec<LogEntry> = entries.by_ref().take(BATCH_ENTRIES).collect();
+            self.queued.fetch_add(batch.len(), Ordering::Relaxed);
+            if !self.send(Ingested::Entries(batch)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Waits for room in the channel, which is the backpressure on the reader.
+    fn send(&self, message: Ingested) -> bool {
+        !self.stop.load(Ordering::Relaxed) && self.sender.send(message).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use super::*;
+    use crate::continuation::Continuation;
+
+    /// A file in the temp directory that tests write to in chunks.
+    struct Chunked {
+        path: PathBuf,
+        file: fs::File,
+    }
+
+    impl Chunked {
+        fn new(name: &str, content: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("multail-ingest-{}-{}.log", name, std::process::id()));
+            fs::write(&path, content).unwrap();
+            let file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+            Self { path, file }
+        }
+
+        fn write(&mut self, content: &str) {
+            self.file.write_all(content.as_bytes()).unwrap();
+        }
+    }
+
+    impl Drop for Chunked {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    fn start(file: &Chunked) -> Ingest {
+        let mut continuation = Continuation::default();
+        continuation.timeout = Duration::from_millis(50);
+        Ingest::start(&file.path, LogParser::with_continuation(continuation)).unwrap()
+    }
+
+    /// Waits for the reader to catch up, then applies what it sent to
+    /// `entries` the way the UI does.
+    fn receive(ingest: &Ingest, entries: &mut Vec<Vec<String>>) {
+        thread::sleep(Duration::from_millis(400));
+        while let Some(message) = ingest.try_recv() {
+            match message {
+                Ingested::Entries(batch) => entries.extend(batch.into_iter().map(|entry| entry.lines)),
+                Ingested::Replaced(entry) => *entries.last_mut().unwrap() = entry.lines,
+                Ingested::Failed(e) => panic!("{}", e),
+                Ingested::Truncated => entries.clear(),
+                Ingested::Loaded { .. } | Ingested::Appended { .. } => {}
+            }
+        }
+    }
+
+    #[test]
+    fn line_written_in_chunks_is_one_entry() {
+        let mut file = Chunked::new("chunks", "[2024-05-01 10:00:00] [INFO] start\n[2024-05-01 10:00:01] [ERROR] hel");
+        let ingest = start(&file);
+        file.write("lo world\n");
+        let mut entries = Vec::new();
+        receive(&ingest, &mut entries);
+        assert_eq!(entries, [
+            vec!["[2024-05-01 10:00:00] [INFO] start"],
+            vec!["[2024-05-01 10:00:01] [ERROR] hello world"],
+        ]);
+    }
+
+    #[test]
+    fn rest_of_flushed_line_completes_it() {
+        let mut file = Chunked::new("flushed", "[2024-05-01 10:00:00] [ERROR] hel");
+        let ingest = start(&file);
+        let mut entries = Vec::new();
+        receive(&ingest, &mut entries);
+        assert_eq!(entries, [vec!["[2024-05-01 10:00:00] [ERROR] hel"]]);
+
+        file.write("lo world\n    at foo\n");
+        receive(&ingest, &mut entries);
+        assert_eq!(entries, [vec!["[2024-05-01 10:00:00] [ERROR] hello world", "    at foo"]]);
+    }
+
+    #[test]
+    fn flushed_continuation_line_is_completed_in_place() {
+        let mut file = Chunked::new("continued", "[2024-05-01 10:00:00] [ERROR] boom\n    at fo");
+        let ingest = start(&file);
+        let mut entries = Vec::new();
+        receive(&ingest, &mut entries);
+        file.write("o\n    at bar\n[2024-05-01 10:00:01] [INFO] next\n");
+        receive(&ingest, &mut entries);
+        assert_eq!(entries, [
+            vec!["[2024-05-01 10:00:00] [ERROR] boom", "    at foo", "    at bar"],
+            vec!["[2024-05-01 10:00:01] [INFO] next"],
+        ]);
+    }
+
+    #[test]
+    fn late_continuation_lines_join_the_shown_entry() {
+        let mut file = Chunked::new("late", "[2024-05-01 10:00:00] [ERROR] boom\n");
+        let ingest = start(&file);
+        let mut entries = Vec::new();
+        receive(&ingest, &mut entries);
+        assert_eq!(entries, [vec!["[2024-05-01 10:00:00] [ERROR] boom"]]);
+
+        file.write("java.lang.IllegalStateException: bad\n");
+        receive(&ingest, &mut entries);
+        file.write("    at Foo.run(Foo.java:10)\n");
+        receive(&ingest, &mut entries);
+        assert_eq!(entries, [vec![
+            "[2024-05-01 10:00:00] [ERROR] boom",
+            "java.lang.IllegalStateException: bad",
+            "    at Foo.run(Foo.java:10)",
+        ]]);
+    }
+
+    #[test]
+    fn last_line_without_newline_is_shown_after_the_timeout() {
+        let file = Chunked::new("unterminated", "[2024-05-01 10:00:00] [INFO] one\n[2024-05-01 10:00:01] [INFO] two");
+        let ingest = start(&file);
+        let mut entries = Vec::new();
+        receive(&ingest, &mut entries);
+        assert_eq!(entries, [
+            vec!["[2024-05-01 10:00:00] [INFO] one"],
+            vec!["[2024-05-01 10:00:01] [INFO] two"],
+        ]);
+    }
+
+    #[test]
+    fn entries_spanning_read_chunks_stay_whole() {
+        let entry = "[2024-05-01 10:00:00] [ERROR] failed with a message long enough\n    at Foo.run(Foo.java:10)\n";
+        let count = 3 * READ_CHUNK / entry.len();
+        let file = Chunked::new("large", &entry.repeat(count));
+        let ingest = start(&file);
+        let mut received = Vec::new();
+        loop {
+            match ingest.receiver.recv_timeout(Duration::from_secs(5)).unwrap() {
+                Ingested::Entries(entries) => received.extend(entries),
+                Ingested::Replaced(entry) => *received.last_mut().unwrap() = entry,
+                Ingested::Loaded { size } => {
+                    assert_eq!(size, fs::metadata(&file.path).unwrap().len());
+                    break;
+                }
+                _ => {}
+            }
+        }
+        // The last entry is held back until the group timeout
+        assert_eq!(received.len(), count - 1);
+        assert!(received.iter().all(|e| e.lines.len() == 2 && e.bytes == entry.len() as u64));
+    }
+
+    #[test]
+    fn truncated_file_is_reported() {
+        let file = Chunked::new("truncated", "[2024-05-01 10:00:00] [INFO] one\n[2024-05-01 10:00:01] [INFO] two\n");
+        let ingest = start(&file);
+        let mut entries = Vec::new();
+        receive(&ingest, &mut entries);
+        assert_eq!(entries.len(), 2);
+
+        file.file.set_len(0).unwrap();
+        receive(&ingest, &mut entries);
+        assert!(entries.is_empty());
+    }
+}
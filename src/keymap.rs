@@ -0,0 +1,556 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crate::config::KeysConfig;
+use crate::ui::UIEvent;
+
+/// A key together with the modifiers held while pressing it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    pub fn from_event(key: &KeyEvent) -> Self {
+        // Shift is already part of the character, e.g. `M` or `?`
+        let modifiers = match key.code {
+            KeyCode::Char(_) => key.modifiers - KeyModifiers::SHIFT,
+            _ => key.modifiers,
+        };
+        Self::new(key.code, modifiers)
+    }
+
+    /// Parses a chord as written in the config file: a single character
+    /// (`G`, `?`), a key name (`Space`, `PgDn`, `F5`), or either with a
+    /// `Ctrl-` prefix (`Ctrl-d`).
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (modifiers, name) = match s.split_once(['-', '+']) {
+            Some((prefix, rest)) if !rest.is_empty() && matches!(prefix.to_ascii_lowercase().as_str(), "ctrl" | "c") => {
+                (KeyModifiers::CONTROL, rest)
+            }
+            _ => (KeyModifiers::NONE, s),
+        };
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match name.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "pgup" | "pageup" => KeyCode::PageUp,
+                "pgdn" | "pagedown" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                lower => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n) => KeyCode::F(n),
+                    None => return Err(format!("unknown key '{}'", s)),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+
+    /// How the key is written in the help overlay and hint bar, e.g. `Ctrl-p` or `Space`.
+    pub fn display(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            KeyCode::Enter => "Enter".to_string(),
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::Backspace => "Backspace".to_string(),
+            KeyCode::PageUp => "PgUp".to_string(),
+            KeyCode::PageDown => "PgDn".to_string(),
+            KeyCode::Home => "Home".to_string(),
+            KeyCode::End => "End".to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            _ => "?".to_string(),
+        };
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            format!("Ctrl-{}", key)
+        } else {
+            key
+        }
+    }
+}
+
+/// Where a binding applies. Keys of the focused panel take precedence over
+/// global ones; popups (prompts, finders, lists) only see their own keys.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    FileList,
+    LogView,
+    Details,
+    Global,
+    Popup,
+}
+
+impl Context {
+    /// Name of the context's table under `[keys]` in the config file.
+    fn config_name(&self) -> &'static str {
+        match self {
+            Context::FileList => "file_list",
+            Context::LogView => "log_view",
+            Context::Details => "details",
+            Context::Global => "global",
+            Context::Popup => "popup",
+        }
+    }
+
+    pub const ALL: [Context; 5] = [Context::FileList, Context::LogView, Context::Details, Context::Global, Context::Popup];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Context::FileList => "File list",
+            Context::LogView => "Log view",
+            Context::Details => "Details pane",
+            Context::Global => "Everywhere",
+            Context::Popup => "Prompts and popups",
+        }
+    }
+}
+
+/// What a key does: an event for the viewer, or something the UI handles
+/// itself (waiting for a mark or level letter, showing the help overlay).
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    Event(UIEvent),
+    SetMark,
+    JumpToMark,
+    NextOf,
+    PreviousOf,
+    Help,
+}
+
+impl Action {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::SetMark => "bookmark entry (+ letter)",
+            Action::JumpToMark => "jump to bookmark (+ letter, [ or ])",
+            Action::NextOf => "next entry (+ e/w/i/d level, m multi-line)",
+            Action::PreviousOf => "previous entry (+ e/w/i/d level, m multi-line)",
+            Action::Help => "help",
+            Action::Event(event) => match event {
+                UIEvent::Quit => "quit",
+                UIEvent::Up => "up",
+                UIEvent::Down => "down",
+                UIEvent::Left => "scroll left / back to files",
+                UIEvent::Right => "scroll right",
+                UIEvent::PageUp => "page up",
+                UIEvent::PageDown => "page down",
+                UIEvent::HalfPageUp => "half page up",
+                UIEvent::HalfPageDown => "half page down",
+                UIEvent::Top => "first line (or line N)",
+                UIEvent::Bottom => "last line (or line N)",
+                UIEvent::NextLevel(_) => "next entry of level",
+                UIEvent::PreviousLevel(_) => "previous entry of level",
+                UIEvent::NextMultiline => "next multi-line entry",
+                UIEvent::PreviousMultiline => "previous multi-line entry",
+                UIEvent::SelectFile(_) => "select file",
+                UIEvent::SelectLine(_) => "select line",
+                UIEvent::ScrollFiles(_) => "scroll files",
+                UIEvent::ScrollLines(_) => "scroll log",
+                UIEvent::JumpToBucket(_) => "jump to time bucket",
+                UIEvent::GrowFileList => "widen file list",
+                UIEvent::ShrinkFileList => "narrow file list",
+                UIEvent::ToggleFileList => "hide/show file list",
+                UIEvent::FileListResized => "file list resized",
+                UIEvent::ToggleExpand => "expand/collapse entry",
+                UIEvent::ExpandAll => "expand all entries",
+                UIEvent::CollapseAll => "collapse all entries",
+                UIEvent::ToggleAutoExpandErrors => "auto-expand errors",
+                UIEvent::ToggleTail => "toggle tail",
+                UIEvent::ScrollLeft => "scroll left",
+                UIEvent::ScrollRight => "scroll right",
+                UIEvent::SwitchToFileList => "back to files",
+                UIEvent::SwitchToLogView => "open log view",
+                UIEvent::OpenFileFinder => "find file",
+                UIEvent::ToggleStats => "statistics panel",
+                UIEvent::ToggleColumns => "column view",
+                UIEvent::ToggleWrap => "wrap lines",
+                UIEvent::CycleLineNumbers => "line numbers / offsets",
+                UIEvent::OpenInEditor => "open in editor",
+                UIEvent::OpenColumns => "choose columns",
+                UIEvent::OpenSort => "sort columns",
+                UIEvent::ToggleDetails => "details pane",
+                UIEvent::FocusDetails => "move focus to/from details",
+                UIEvent::FilterByField => "filter by field",
+                UIEvent::ClearFilter => "clear filters",
+                UIEvent::ToggleTimeline => "timeline",
+                UIEvent::PreviousTimeBucket => "previous time bucket",
+                UIEvent::NextTimeBucket => "next time bucket",
+                UIEvent::OpenGoto => "go to line or time",
+                UIEvent::OpenQuery => "query",
+                UIEvent::SetMark(_) => "bookmark entry",
+                UIEvent::JumpToMark(_) => "jump to bookmark",
+                UIEvent::OpenBookmarks => "list bookmarks",
+                UIEvent::ToggleSelection => "start/clear selection",
+                UIEvent::OpenExport => "save view to file",
+                UIEvent::Input(_) => "type",
+                UIEvent::Backspace => "delete character",
+                UIEvent::Confirm => "confirm",
+                UIEvent::Cancel => "cancel",
+            },
+        }
+    }
+}
+
+/// Action names for the config file.
+const ACTION_NAMES: &[(&str, Action)] = &[
+    ("quit", Action::Event(UIEvent::Quit)),
+    ("up", Action::Event(UIEvent::Up)),
+    ("down", Action::Event(UIEvent::Down)),
+    ("left", Action::Event(UIEvent::Left)),
+    ("right", Action::Event(UIEvent::Right)),
+    ("page_up", Action::Event(UIEvent::PageUp)),
+    ("page_down", Action::Event(UIEvent::PageDown)),
+    ("half_page_up", Action::Event(UIEvent::HalfPageUp)),
+    ("half_page_down", Action::Event(UIEvent::HalfPageDown)),
+    ("top", Action::Event(UIEvent::Top)),
+    ("bottom", Action::Event(UIEvent::Bottom)),
+    ("toggle_expand", Action::Event(UIEvent::ToggleExpand)),
+    ("expand_all", Action::Event(UIEvent::ExpandAll)),
+    ("collapse_all", Action::Event(UIEvent::CollapseAll)),
+    ("auto_expand_errors", Action::Event(UIEvent::ToggleAutoExpandErrors)),
+    ("toggle_tail", Action::Event(UIEvent::ToggleTail)),
+    ("scroll_left", Action::Event(UIEvent::ScrollLeft)),
+    ("scroll_right", Action::Event(UIEvent::ScrollRight)),
+    ("file_list", Action::Event(UIEvent::SwitchToFileList)),
+    ("log_view", Action::Event(UIEvent::SwitchToLogView)),
+    ("find_file", Action::Event(UIEvent::OpenFileFinder)),
+    ("toggle_stats", Action::Event(UIEvent::ToggleStats)),
+    ("toggle_columns", Action::Event(UIEvent::ToggleColumns)),
+    ("toggle_wrap", Action::Event(UIEvent::ToggleWrap)),
+    ("cycle_line_numbers", Action::Event(UIEvent::CycleLineNumbers)),
+    ("open_in_editor", Action::Event(UIEvent::OpenInEditor)),
+    ("choose_columns", Action::Event(UIEvent::OpenColumns)),
+    ("sort", Action::Event(UIEvent::OpenSort)),
+    ("toggle_details", Action::Event(UIEvent::ToggleDetails)),
+    ("focus_details", Action::Event(UIEvent::FocusDetails)),
+    ("filter_by_field", Action::Event(UIEvent::FilterByField)),
+    ("clear_filter", Action::Event(UIEvent::ClearFilter)),
+    ("toggle_timeline", Action::Event(UIEvent::ToggleTimeline)),
+    ("previous_bucket", Action::Event(UIEvent::PreviousTimeBucket)),
+    ("next_bucket", Action::Event(UIEvent::NextTimeBucket)),
+    ("grow_file_list", Action::Event(UIEvent::GrowFileList)),
+    ("shrink_file_list", Action::Event(UIEvent::ShrinkFileList)),
+    ("toggle_file_list", Action::Event(UIEvent::ToggleFileList)),
+    ("goto", Action::Event(UIEvent::OpenGoto)),
+    ("query", Action::Event(UIEvent::OpenQuery)),
+    ("set_mark", Action::SetMark),
+    ("jump_to_mark", Action::JumpToMark),
+    ("next_entry", Action::NextOf),
+    ("previous_entry", Action::PreviousOf),
+    ("bookmarks", Action::Event(UIEvent::OpenBookmarks)),
+    ("toggle_selection", Action::Event(UIEvent::ToggleSelection)),
+    ("export", Action::Event(UIEvent::OpenExport)),
+    ("help", Action::Help),
+    ("confirm", Action::Event(UIEvent::Confirm)),
+    ("cancel", Action::Event(UIEvent::Cancel)),
+    ("backspace", Action::Event(UIEvent::Backspace)),
+];
+
+pub struct Binding {
+    pub context: Context,
+    pub key: KeyChord,
+    pub action: Action,
+}
+
+const fn key(c: char) -> KeyChord {
+    KeyChord::new(KeyCode::Char(c), KeyModifiers::NONE)
+}
+
+const fn special(code: KeyCode) -> KeyChord {
+    KeyChord::new(code, KeyModifiers::NONE)
+}
+
+const fn ctrl(c: char) -> KeyChord {
+    KeyChord::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+}
+
+const DEFAULT_BINDINGS: &[(Context, KeyChord, Action)] = &[
+    (Context::FileList, special(KeyCode::Up), Action::Event(UIEvent::Up)),
+    (Context::FileList, special(KeyCode::Down), Action::Event(UIEvent::Down)),
+    (Context::FileList, special(KeyCode::PageUp), Action::Event(UIEvent::PageUp)),
+    (Context::FileList, special(KeyCode::PageDown), Action::Event(UIEvent::PageDown)),
+    (Context::FileList, special(KeyCode::Home), Action::Event(UIEvent::Top)),
+    (Context::FileList, special(KeyCode::End), Action::Event(UIEvent::Bottom)),
+    (Context::FileList, special(KeyCode::Enter), Action::Event(UIEvent::SwitchToLogView)),
+    (Context::FileList, special(KeyCode::Right), Action::Event(UIEvent::Right)),
+    (Context::LogView, special(KeyCode::Up), Action::Event(UIEvent::Up)),
+    (Context::LogView, special(KeyCode::Down), Action::Event(UIEvent::Down)),
+    (Context::LogView, special(KeyCode::PageUp), Action::Event(UIEvent::PageUp)),
+    (Context::LogView, special(KeyCode::PageDown), Action::Event(UIEvent::PageDown)),
+    (Context::LogView, ctrl('u'), Action::Event(UIEvent::HalfPageUp)),
+    (Context::LogView, ctrl('d'), Action::Event(UIEvent::HalfPageDown)),
+    (Context::LogView, special(KeyCode::Home), Action::Event(UIEvent::Top)),
+    (Context::LogView, special(KeyCode::End), Action::Event(UIEvent::Bottom)),
+    (Context::LogView, key('}'), Action::NextOf),
+    (Context::LogView, key('{'), Action::PreviousOf),
+    (Context::LogView, special(KeyCode::Left), Action::Event(UIEvent::Left)),
+    (Context::LogView, special(KeyCode::Right), Action::Event(UIEvent::Right)),
+    (Context::LogView, key('h'), Action::Event(UIEvent::ScrollLeft)),
+    (Context::LogView, key('l'), Action::Event(UIEvent::ScrollRight)),
+    (Context::LogView, key(' '), Action::Event(UIEvent::ToggleExpand)),
+    (Context::LogView, key('E'), Action::Event(UIEvent::ExpandAll)),
+    (Context::LogView, key('Z'), Action::Event(UIEvent::CollapseAll)),
+    (Context::LogView, key('A'), Action::Event(UIEvent::ToggleAutoExpandErrors)),
+    (Context::LogView, key('t'), Action::Event(UIEvent::ToggleTail)),
+    (Context::LogView, special(KeyCode::Esc), Action::Event(UIEvent::SwitchToFileList)),
+    (Context::LogView, key('['), Action::Event(UIEvent::PreviousTimeBucket)),
+    (Context::LogView, key(']'), Action::Event(UIEvent::NextTimeBucket)),
+    (Context::LogView, key('m'), Action::SetMark),
+    (Context::LogView, key('\''), Action::JumpToMark),
+    (Context::LogView, key('v'), Action::Event(UIEvent::ToggleSelection)),
+    (Context::LogView, key('e'), Action::Event(UIEvent::OpenInEditor)),
+    (Context::Details, special(KeyCode::Up), Action::Event(UIEvent::Up)),
+    (Context::Details, special(KeyCode::Down), Action::Event(UIEvent::Down)),
+    (Context::Details, key('f'), Action::Event(UIEvent::FilterByField)),
+    (Context::Details, special(KeyCode::Esc), Action::Event(UIEvent::FocusDetails)),
+    (Context::Details, special(KeyCode::Left), Action::Event(UIEvent::FocusDetails)),
+    (Context::Global, key('?'), Action::Help),
+    (Context::Global, key('q'), Action::Event(UIEvent::Quit)),
+    (Context::Global, ctrl('p'), Action::Event(UIEvent::OpenFileFinder)),
+    (Context::Global, key(':'), Action::Event(UIEvent::OpenGoto)),
+    (Context::Global, key(';'), Action::Event(UIEvent::OpenQuery)),
+    (Context::Global, key('M'), Action::Event(UIEvent::OpenBookmarks)),
+    (Context::Global, key('s'), Action::Event(UIEvent::OpenExport)),
+    (Context::Global, key('d'), Action::Event(UIEvent::ToggleDetails)),
+    (Context::Global, special(KeyCode::Tab), Action::Event(UIEvent::FocusDetails)),
+    (Context::Global, key('F'), Action::Event(UIEvent::ClearFilter)),
+    (Context::Global, key('i'), Action::Event(UIEvent::ToggleStats)),
+    (Context::Global, key('H'), Action::Event(UIEvent::ToggleTimeline)),
+    (Context::Global, key('c'), Action::Event(UIEvent::ToggleColumns)),
+    (Context::Global, key('C'), Action::Event(UIEvent::OpenColumns)),
+    (Context::Global, key('o'), Action::Event(UIEvent::OpenSort)),
+    (Context::Global, key('w'), Action::Event(UIEvent::ToggleWrap)),
+    (Context::Global, key('#'), Action::Event(UIEvent::CycleLineNumbers)),
+    (Context::Global, key('+'), Action::Event(UIEvent::GrowFileList)),
+    (Context::Global, key('='), Action::Event(UIEvent::GrowFileList)),
+    (Context::Global, key('-'), Action::Event(UIEvent::ShrinkFileList)),
+    (Context::Global, key('\\'), Action::Event(UIEvent::ToggleFileList)),
+    (Context::Popup, special(KeyCode::Enter), Action::Event(UIEvent::Confirm)),
+    (Context::Popup, special(KeyCode::Esc), Action::Event(UIEvent::Cancel)),
+    (Context::Popup, special(KeyCode::Up), Action::Event(UIEvent::Up)),
+    (Context::Popup, special(KeyCode::Down), Action::Event(UIEvent::Down)),
+    (Context::Popup, special(KeyCode::Backspace), Action::Event(UIEvent::Backspace)),
+];
+
+/// Bindings the vim preset adds on top of the defaults.
+const VIM_BINDINGS: &[(Context, KeyChord, Action)] = &[
+    (Context::FileList, key('j'), Action::Event(UIEvent::Down)),
+    (Context::FileList, key('k'), Action::Event(UIEvent::Up)),
+    (Context::FileList, key('g'), Action::Event(UIEvent::Top)),
+    (Context::FileList, key('G'), Action::Event(UIEvent::Bottom)),
+    (Context::FileList, key('l'), Action::Event(UIEvent::Right)),
+    (Context::LogView, key('j'), Action::Event(UIEvent::Down)),
+    (Context::LogView, key('k'), Action::Event(UIEvent::Up)),
+    (Context::LogView, key('g'), Action::Event(UIEvent::Top)),
+    (Context::LogView, key('G'), Action::Event(UIEvent::Bottom)),
+    (Context::LogView, ctrl('f'), Action::Event(UIEvent::PageDown)),
+    (Context::LogView, ctrl('b'), Action::Event(UIEvent::PageUp)),
+    (Context::Details, key('j'), Action::Event(UIEvent::Down)),
+    (Context::Details, key('k'), Action::Event(UIEvent::Up)),
+    (Context::Details, key('h'), Action::Event(UIEvent::FocusDetails)),
+];
+
+/// Bindings the less preset adds on top of the defaults. Space pages like
+/// in less, so expanding an entry moves to Enter, and `F` follows the file,
+/// so clearing the filter moves to `&` (an empty `&` pattern in less).
+const LESS_BINDINGS: &[(Context, KeyChord, Action)] = &[
+    (Context::FileList, key('j'), Action::Event(UIEvent::Down)),
+    (Context::FileList, key('k'), Action::Event(UIEvent::Up)),
+    (Context::LogView, key('j'), Action::Event(UIEvent::Down)),
+    (Context::LogView, key('k'), Action::Event(UIEvent::Up)),
+    (Context::LogView, key(' '), Action::Event(UIEvent::PageDown)),
+    (Context::LogView, key('f'), Action::Event(UIEvent::PageDown)),
+    (Context::LogView, key('b'), Action::Event(UIEvent::PageUp)),
+    (Context::LogView, key('g'), Action::Event(UIEvent::Top)),
+    (Context::LogView, key('<'), Action::Event(UIEvent::Top)),
+    (Context::LogView, key('G'), Action::Event(UIEvent::Bottom)),
+    (Context::LogView, key('>'), Action::Event(UIEvent::Bottom)),
+    (Context::LogView, key('F'), Action::Event(UIEvent::ToggleTail)),
+    (Context::LogView, special(KeyCode::Enter), Action::Event(UIEvent::ToggleExpand)),
+    (Context::Global, key('&'), Action::Event(UIEvent::ClearFilter)),
+];
+
+/// The key bindings of the viewer, which also drive the help overlay and
+/// the hint bar so they can't drift from what the keys actually do.
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = DEFAULT_BINDINGS
+            .iter()
+            .map(|&(context, key, action)| Binding { context, key, action })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Builds the keymap from the `[keys]` config: the defaults, then the
+    /// preset's bindings, then the user's own. Chords in the same context
+    /// that only differ in spelling, digits (which are count prefixes), and
+    /// global bindings a panel would always shadow are reported as errors.
+    pub fn from_config(config: &KeysConfig) -> Result<Self, String> {
+        let mut keymap = Self::default();
+        let preset = match config.preset.as_deref() {
+            None | Some("default") => &[][..],
+            Some("vim") => VIM_BINDINGS,
+            Some("less") => LESS_BINDINGS,
+            Some(other) => return Err(format!("keys.preset: unknown preset '{}' (expected default, vim or less)", other)),
+        };
+        for &(context, key, action) in preset {
+            keymap.bind(context, key, Some(action));
+        }
+
+        let tables = [
+            (Context::FileList, &config.file_list),
+            (Context::LogView, &config.log_view),
+            (Context::Details, &config.details),
+            (Context::Global, &config.global),
+            (Context::Popup, &config.popup),
+        ];
+        let mut user_bindings: Vec<(Context, KeyChord, &str)> = Vec::new();
+        for (context, table) in tables {
+            let section = format!("keys.{}", context.config_name());
+            for (chord, action_name) in table {
+                let key = KeyChord::parse(chord).map_err(|e| format!("{}: {}", section, e))?;
+                if let KeyCode::Char('0'..='9') = key.code {
+                    if key.modifiers.is_empty() && context != Context::Popup {
+                        return Err(format!("{}: '{}' can't be bound, digits are count prefixes", section, chord));
+                    }
+                }
+                if let Some((_, _, other)) = user_bindings.iter().find(|(c, k, _)| *c == context && *k == key) {
+                    return Err(format!("{}: '{}' and '{}' are the same key", section, other, chord));
+                }
+                // `none` unbinds a default or preset key
+                let action = match action_name.as_str() {
+                    "none" => None,
+                    name => match ACTION_NAMES.iter().find(|(n, _)| *n == name) {
+                        Some(&(_, action)) => Some(action),
+                        None => return Err(format!("{}: unknown action '{}' for '{}'", section, name, chord)),
+                    },
+                };
+                keymap.bind(context, key, action);
+                user_bindings.push((context, key, chord));
+            }
+        }
+
+        for &(_, key, chord) in user_bindings.iter().filter(|(context, _, _)| *context == Context::Global) {
+            let shadowing = keymap.bindings
+                .iter()
+                .find(|b| b.key == key && !matches!(b.context, Context::Global | Context::Popup));
+            if let Some(binding) = shadowing {
+                return Err(format!(
+                    "keys.global: '{}' is also bound in keys.{} ({}), so it never applies there",
+                    chord,
+                    binding.context.config_name(),
+                    binding.action.description(),
+                ));
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// Binds a key in a context, replacing whatever it did there; `None` unbinds it.
+    fn bind(&mut self, context: Context, key: KeyChord, action: Option<Action>) {
+        match self.bindings.iter().position(|b| b.context == context && b.key == key) {
+            Some(i) => match action {
+                Some(action) => self.bindings[i].action = action,
+                None => {
+                    self.bindings.remove(i);
+                }
+            },
+            None => {
+                if let Some(action) = action {
+                    self.bindings.push(Binding { context, key, action });
+                }
+            }
+        }
+    }
+
+    /// Finds the action for a key, trying the context's own bindings before global ones.
+    pub fn lookup(&self, context: Context, key: KeyChord) -> Option<Action> {
+        let find = |context| {
+            self.bindings
+                .iter()
+                .find(|b| b.context == context && b.key == key)
+                .map(|b| b.action)
+        };
+        match context {
+            Context::Popup => find(Context::Popup),
+            _ => find(context).or_else(|| find(Context::Global)),
+        }
+    }
+
+    /// Bindings of one context, with keys for the same action joined, e.g. `Esc/←`.
+    pub fn section(&self, context: Context) -> Vec<(String, &'static str)> {
+        let mut rows: Vec<(Vec<String>, Action)> = Vec::new();
+        for binding in self.bindings.iter().filter(|b| b.context == context) {
+            match rows.iter_mut().find(|(_, action)| *action == binding.action) {
+                Some((keys, _)) => keys.push(binding.key.display()),
+                None => rows.push((vec![binding.key.display()], binding.action)),
+            }
+        }
+        rows.into_iter()
+            .map(|(keys, action)| (keys.join("/"), action.description()))
+            .collect()
+    }
+
+    /// All keys bound to an action, e.g. `?` for help.
+    pub fn keys_for(&self, action: Action) -> Option<String> {
+        let keys: Vec<String> = self.bindings
+            .iter()
+            .filter(|b| b.action == action)
+            .map(|b| b.key.display())
+            .collect();
+        (!keys.is_empty()).then(|| keys.join("/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Presets may take over default keys, but each global action must
+    /// still have a key that reaches it from every panel.
+    #[test]
+    fn presets_leave_every_global_action_reachable() {
+        for preset in ["default", "vim", "less"] {
+            let config = KeysConfig { preset: Some(preset.to_string()), ..KeysConfig::default() };
+            let keymap = Keymap::from_config(&config).unwrap();
+            for global in keymap.bindings.iter().filter(|b| b.context == Context::Global) {
+                for context in [Context::FileList, Context::LogView, Context::Details] {
+                    let reachable = keymap.bindings
+                        .iter()
+                        .filter(|b| b.action == global.action)
+                        .any(|b| keymap.lookup(context, b.key) == Some(global.action));
+                    assert!(
+                        reachable,
+                        "{} preset: nothing reaches '{}' in keys.{}",
+                        preset,
+                        global.action.description(),
+                        context.config_name()
+                    );
+                }
+            }
+        }
+    }
+}
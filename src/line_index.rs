@@ -0,0 +1,333 @@
+use std::collections::HashSet;
+use crate::log_parser::LogEntry;
+
+/// Line counts of the entries in the order the log view shows them, kept in
+/// a Fenwick tree so screen lines and entries map onto each other, and an
+/// entry's height changes or a new entry is appended, in O(log n). Also keeps
+/// where each entry starts in the file, for the source position gutter.
+#[derive(Default)]
+pub struct LineIndex {
+    /// Entry index at each display position; empty when shown in file order.
+    order: Vec<usize>,
+    /// Display position of each entry, the inverse of `order`.
+    positions: Vec<usize>,
+    /// Lines shown by each display position.
+    heights: Vec<usize>,
+    /// Fenwick tree over `heights`: node `i` (1-based) sums the heights of
+    /// the `i & i.wrapping_neg()` positions ending at position `i - 1`.
+    tree: Vec<usize>,
+    total: usize,
+    /// 1-based file line and byte offset of the first line of each entry.
+    file_starts: Vec<(usize, u64)>,
+    file_end: (usize, u64),
+}
+
+impl LineIndex {
+    /// Indexes `entries`, the first of which starts at file line and byte
+    /// offset `file_start`.
+    pub fn build(
+        entries: &[LogEntry],
+        file_start: (usize, u64),
+        row_order: &[usize],
+        expanded_entries: &HashSet<usize>,
+        hidden_entries: &HashSet<usize>,
+    ) -> Self {
+        let mut index = Self {
+            order: row_order.to_vec(),
+            positions: Vec::new(),
+            heights: Vec::with_capacity(entries.len()),
+            tree: Vec::with_capacity(entries.len()),
+            total: 0,
+            file_starts: Vec::with_capacity(entries.len()),
+            file_end: file_start,
+        };
+        if !row_order.is_empty() {
+            index.positions = vec![0; entries.len()];
+            for (position, &entry) in row_order.iter().enumerate() {
+                index.positions[entry] = position;
+            }
+        }
+        index.heights = (0..entries.len())
+            .map(|position| {
+                let entry = index.entry_at_position(position);
+                entry_height(&entries[entry], entry, expanded_entries, hidden_entries)
+            })
+            .collect();
+        index.total = index.heights.iter().sum();
+        // Linear construction: each node passes its sum on to its parent
+        index.tree = index.heights.clone();
+        for i in 1..=index.tree.len() {
+            let parent = i + (i & i.wrapping_neg());
+            if parent <= index.tree.len() {
+                index.tree[parent - 1] += index.tree[i - 1];
+            }
+        }
+        index.push_file_positions(entries);
+        index
+    }
+
+    /// Adds entries appended to the file. Only valid in file order; a sorted
+    /// view has to be rebuilt.
+    pub fn extend(&mut self, entries: &[LogEntry], expanded_entries: &HashSet<usize>, hidden_entries: &HashSet<usize>) {
+        for (entry, log_entry) in entries.iter().enumerate().skip(self.len()) {
+            self.push(entry_height(log_entry, entry, expanded_entries, hidden_entries));
+        }
+        self.push_file_positions(entries);
+    }
+
+    /// Appends a display position. Its node covers the positions just
+    /// before it, whose sum is the difference of two prefix sums.
+    fn push(&mut self, height: usize) {
+        let i = self.heights.len() + 1;
+        let node = height + self.prefix(i - 1) - self.prefix(i - (i & i.wrapping_neg()));
+        self.heights.push(height);
+        self.tree.push(node);
+        self.total += height;
+    }
+
+    /// Changes the number of lines an entry shows, as expanding or
+    /// collapsing it does.
+    pub fn set_height(&mut self, entry: usize, height: usize) {
+        if entry >= self.len() {
+            return;
+        }
+        let position = self.position_of(entry);
+        let old = std::mem::replace(&mut self.heights[position], height);
+        let mut i = position + 1;
+        while i <= self.tree.len() {
+            self.tree[i - 1] = self.tree[i - 1] + height - old;
+            i += i & i.wrapping_neg();
+        }
+        self.total = self.total + height - old;
+    }
+
+    /// Updates the last entry after it was read further in the file.
+    pub fn last_entry_changed(&mut self, entries: &[LogEntry], height: usize) {
+        let Some(last) = self.file_starts.len().checked_sub(1) else {
+            return;
+        };
+        let start = self.file_starts[last];
+        self.file_end = (start.0 + entries[last].lines.len(), start.1 + entries[last].bytes);
+        self.set_height(last, height);
+    }
+
+    /// Lines shown by the first `count` display positions.
+    fn prefix(&self, count: usize) -> usize {
+        let mut sum = 0;
+        let mut i = count;
+        while i > 0 {
+            sum += self.tree[i - 1];
+            i -= i & i.wrapping_neg();
+        }
+        sum
+    }
+
+    fn push_file_positions(&mut self, entries: &[LogEntry]) {
+        for entry in &entries[self.file_starts.len()..] {
+            self.file_starts.push(self.file_end);
+            self.file_end.0 += entry.lines.len();
+            self.file_end.1 += entry.bytes;
+        }
+    }
+
+    /// Number of entries indexed.
+    pub fn len(&self) -> usize {
+        self.heights.len()
+    }
+
+    pub fn is_sorted(&self) -> bool {
+        !self.order.is_empty()
+    }
+
+    /// Number of lines the log view shows.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub fn entry_at_position(&self, position: usize) -> usize {
+        if self.order.is_empty() { position } else { self.order[position] }
+    }
+
+    pub fn position_of(&self, entry: usize) -> usize {
+        if self.order.is_empty() { entry } else { self.positions[entry] }
+    }
+
+    /// Maps a visible line to the entry shown there. Past the end, falls
+    /// back to the last entry that is shown.
+    pub fn entry_at_line(&self, line: usize) -> usize {
+        let line = if line < self.total() {
+            line
+        } else {
+            match self.total().checked_sub(1) {
+                Some(last) => last,
+                None => return self.len().saturating_sub(1),
+            }
+        };
+        // Descend the tree for the most positions whose lines all end at or
+        // before `line`; the next position is the one showing it
+        let mut position = 0;
+        let mut remaining = line;
+        let mut step = self.tree.len().next_power_of_two();
+        while step > 0 {
+            let next = position + step;
+            if next <= self.tree.len() && self.tree[next - 1] <= remaining {
+                position = next;
+                remaining -= self.tree[next - 1];
+            }
+            step /= 2;
+        }
+        self.entry_at_position(position)
+    }
+
+    /// The visible lines of an entry, as a start and end (exclusive). An
+    /// index past the last entry gives the end of the view.
+    pub fn lines_of_entry(&self, entry: usize) -> (usize, usize) {
+        if entry >= self.len() {
+            return (self.total(), self.total());
+        }
+        let position = self.position_of(entry);
+        let start = self.prefix(position);
+        (start, start + self.heights[position])
+    }
+
+    /// 1-based file line and byte offset of the first line of an entry.
+    pub fn file_position(&self, entry: usize) -> (usize, u64) {
+        self.file_starts.get(entry).copied().unwrap_or(self.file_end)
+    }
+
+    /// The file line and byte offset just past the last entry.
+    pub fn file_end(&self) -> (usize, u64) {
+        self.file_end
+    }
+}
+
+/// Number of lines an entry occupies in the log view.
+pub fn entry_height(entry: &LogEntry, index: usize, expanded_entries: &HashSet<usize>, hidden_entries: &HashSet<usize>) -> usize {
+    if hidden_entries.contains(&index) {
+        0
+    } else if expanded_entries.contains(&index) {
+        entry.lines.len()
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_parser::LogLevel;
+
+    /// A xorshift generator, enough to drive the operations reproducibly.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    fn entry(rng: &mut Rng) -> LogEntry {
+        let lines: Vec<String> = (0..1 + rng.below(4)).map(|i| "x".repeat(i + rng.below(20))).collect();
+        LogEntry {
+            timestamp: String::new(),
+            time: None,
+            level: LogLevel::Info,
+            message: lines[0].clone(),
+            bytes: lines.iter().map(|line| line.len() as u64 + 1).sum(),
+            lines,
+        }
+    }
+
+    /// Checks `index` against one built from scratch over the same state.
+    fn assert_consistent(index: &LineIndex, entries: &[LogEntry], order: &[usize], expanded: &HashSet<usize>, hidden: &HashSet<usize>) {
+        let fresh = LineIndex::build(entries, (1, 0), order, expanded, hidden);
+        assert_eq!(index.len(), fresh.len());
+        assert_eq!(index.total(), fresh.total());
+        assert_eq!(index.file_end(), fresh.file_end());
+        for entry in 0..entries.len() {
+            assert_eq!(index.position_of(entry), fresh.position_of(entry));
+            assert_eq!(index.lines_of_entry(entry), fresh.lines_of_entry(entry));
+            assert_eq!(index.file_position(entry), fresh.file_position(entry));
+        }
+        for line in 0..fresh.total() + 2 {
+            assert_eq!(index.entry_at_line(line), fresh.entry_at_line(line), "line {}", line);
+        }
+    }
+
+    fn height(entries: &[LogEntry], entry: usize, expanded: &HashSet<usize>, hidden: &HashSet<usize>) -> usize {
+        entry_height(&entries[entry], entry, expanded, hidden)
+    }
+
+    /// Toggles `entry` in `set`.
+    fn toggle(set: &mut HashSet<usize>, entry: usize) {
+        if !set.remove(&entry) {
+            set.insert(entry);
+        }
+    }
+
+    #[test]
+    fn random_operations_in_file_order() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let (mut expanded, mut hidden) = (HashSet::new(), HashSet::new());
+        let mut entries: Vec<LogEntry> = (0..10).map(|_| entry(&mut rng)).collect();
+        let mut index = LineIndex::build(&entries, (1, 0), &[], &expanded, &hidden);
+        for step in 0..5000 {
+            match rng.below(5) {
+                0 => {
+                    for _ in 0..1 + rng.below(3) {
+                        entries.push(entry(&mut rng));
+                    }
+                    index.extend(&entries, &expanded, &hidden);
+                }
+                1 => {
+                    let e = rng.below(entries.len());
+                    toggle(&mut expanded, e);
+                    index.set_height(e, height(&entries, e, &expanded, &hidden));
+                }
+                2 => {
+                    let e = rng.below(entries.len());
+                    toggle(&mut hidden, e);
+                    index.set_height(e, height(&entries, e, &expanded, &hidden));
+                }
+                3 => {
+                    let last = entries.len() - 1;
+                    entries[last].lines.push("  at frame".to_string());
+                    entries[last].bytes += 11;
+                    index.last_entry_changed(&entries, height(&entries, last, &expanded, &hidden));
+                }
+                _ => {
+                    let e = rng.below(entries.len());
+                    index.set_height(e, height(&entries, e, &expanded, &hidden));
+                }
+            }
+            if step % 50 == 0 {
+                assert_consistent(&index, &entries, &[], &expanded, &hidden);
+            }
+        }
+        assert_consistent(&index, &entries, &[], &expanded, &hidden);
+    }
+
+    #[test]
+    fn random_heights_in_sorted_order() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let (mut expanded, mut hidden) = (HashSet::new(), HashSet::new());
+        let entries: Vec<LogEntry> = (0..500).map(|_| entry(&mut rng)).collect();
+        let mut order: Vec<usize> = (0..entries.len()).collect();
+        for i in (1..order.len()).rev() {
+            order.swap(i, rng.below(i + 1));
+        }
+        let mut index = LineIndex::build(&entries, (1, 0), &order, &expanded, &hidden);
+        for step in 0..5000 {
+            let e = rng.below(entries.len());
+            toggle(if rng.below(3) == 0 { &mut hidden } else { &mut expanded }, e);
+            index.set_height(e, height(&entries, e, &expanded, &hidden));
+            if step % 50 == 0 {
+                assert_consistent(&index, &entries, &order, &expanded, &hidden);
+            }
+        }
+        assert_consistent(&index, &entries, &order, &expanded, &hidden);
+    }
+}
@@ -1,14 +1,21 @@
+use std::time::Duration;
+use chrono::{DateTime, NaiveDateTime};
 use regex::Regex;
+use crate::continuation::Continuation;
 
 #[derive(Debug, Clone)]
 pub struct LogEntry {
-    pub _timestamp: String,
+    pub timestamp: String,
+    pub time: Option<NaiveDateTime>,
     pub level: LogLevel,
-    pub _message: String,
+    pub message: String,
     pub lines: Vec<String>,
+    /// Length of the entry in the file, line endings included, which the
+    /// lines alone don't give for CRLF files or invalid UTF-8.
+    pub bytes: u64,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum LogLevel {
     Debug,
     Info,
@@ -27,6 +34,17 @@ impl LogLevel {
         }
     }
 
+    pub const ALL: [LogLevel; 4] = [LogLevel::Debug, LogLevel::Info, LogLevel::Warn, LogLevel::Error];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+
     pub fn _color(&self) -> &'static str {
         match self {
             LogLevel::Debug => "\x1b[90m",  // Gray
@@ -37,51 +55,112 @@ impl LogLevel {
     }
 }
 
+#[derive(Clone)]
 pub struct LogParser {
     regex: Regex,
+    continuation: Continuation,
 }
 
 impl LogParser {
-    pub fn new() -> Self {
+    pub fn with_continuation(continuation: Continuation) -> Self {
         let regex = Regex::new(r"^\[(.*?)\] \[(DEBUG|INFO|WARN|ERROR)\] (.*)").unwrap();
-        Self { regex }
+        Self { regex, continuation }
+    }
+
+    /// How long the last entry of a tailed file waits for more lines.
+    pub fn group_timeout(&self) -> Duration {
+        self.continuation.timeout
+    }
+
+    pub fn parse(&self, content: &[u8]) -> Vec<LogEntry> {
+        let mut open = None;
+        let mut entries = self.parse_continuing(content, &mut open);
+        entries.extend(open);
+        entries
     }
 
-    pub fn parse(&self, content: &str) -> Vec<LogEntry> {
+    /// Parses `content` following on from `open`, the last entry of the
+    /// previous call, which its first lines may continue. Returns the
+    /// entries that are complete and leaves the last one in `open`.
+    /// Invalid UTF-8 is replaced, but each entry still counts its bytes as
+    /// they are in the file.
+    pub fn parse_continuing(&self, content: &[u8], open: &mut Option<LogEntry>) -> Vec<LogEntry> {
         let mut entries = Vec::new();
-        let mut current_entry: Option<LogEntry> = None;
 
-        for line in content.lines() {
+        for raw in content.split_inclusive(|&b| b == b'\n') {
+            let bytes = raw.len() as u64;
+            let text = raw.strip_suffix(b"\n").unwrap_or(raw);
+            let text = String::from_utf8_lossy(text.strip_suffix(b"\r").unwrap_or(text));
+            let line = text.as_ref();
             if let Some(caps) = self.regex.captures(line) {
-                if let Some(entry) = current_entry.take() {
-                    entries.push(entry);
-                }
+                entries.extend(open.take());
 
                 if let Some(level) = LogLevel::from_str(&caps[2]) {
-                    current_entry = Some(LogEntry {
-                        _timestamp: caps[1].to_string(),
+                    *open = Some(LogEntry {
+                        timestamp: caps[1].to_string(),
+                        time: parse_timestamp(&caps[1]),
                         level,
-                        _message: caps[3].to_string(),
+                        message: caps[3].to_string(),
                         lines: vec![line.to_string()],
+                        bytes,
                     });
                 }
-            } else if let Some(entry) = &mut current_entry {
+            } else if let Some(entry) = open.as_mut().filter(|entry| self.continuation.continues(line, entry.lines.len())) {
                 entry.lines.push(line.to_string());
+                entry.bytes += bytes;
             } else {
-                // If no current entry and line doesn't match regex, create a new entry
-                entries.push(LogEntry {
-                    _timestamp: "".to_string(),
+                // A line that doesn't continue the entry above starts one of
+                // its own, which stack trace lines can in turn continue
+                entries.extend(open.take());
+                *open = Some(LogEntry {
+                    timestamp: "".to_string(),
+                    time: None,
                     level: LogLevel::Debug,
-                    _message: line.to_string(),
+                    message: line.to_string(),
                     lines: vec![line.to_string()],
+                    bytes,
                 });
             }
         }
 
-        if let Some(entry) = current_entry {
-            entries.push(entry);
-        }
-
         entries
     }
-} 
\ No newline at end of file
+}
+
+/// Parses the bracketed timestamp of a log line, trying the common
+/// ISO-8601-like layouts. Timezone offsets are dropped.
+pub fn parse_timestamp(s: &str) -> Option<NaiveDateTime> {
+    const FORMATS: [&str; 3] = [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y/%m/%d %H:%M:%S%.f",
+    ];
+
+    let s = s.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt.naive_local());
+    }
+    FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+}
+
+/// Maps a 1-based file line number to the entry containing that line.
+/// `first_line` is the file line `entries` start at, which is 1 unless
+/// older entries were evicted.
+pub fn entry_at_file_line(entries: &[LogEntry], first_line: usize, line: usize) -> usize {
+    let mut line_count = first_line - 1;
+    for (i, entry) in entries.iter().enumerate() {
+        line_count += entry.lines.len();
+        if line <= line_count {
+            return i;
+        }
+    }
+    entries.len().saturating_sub(1)
+}
+
+/// Returns the 1-based file line number of the first line of an entry, in
+/// `entries` starting at `first_line`.
+pub fn file_line_of_entry(entries: &[LogEntry], first_line: usize, index: usize) -> usize {
+    entries[..index.min(entries.len())].iter().map(|e| e.lines.len()).sum::<usize>() + first_line
+}
@@ -1,17 +1,95 @@
 use std::env;
 use std::path::PathBuf;
 use std::error::Error;
+use std::process;
 
 mod app;
 mod ui;
 mod log_parser;
+mod fuzzy;
+mod ingest;
+mod keymap;
+mod line_index;
+mod stats;
+mod timeline;
+mod goto;
+mod bookmarks;
+mod expansions;
+mod export;
+mod filter;
+mod cli;
+mod config;
+mod pipeline;
+mod fields;
+mod columns;
+mod continuation;
+mod query;
+mod retention;
+mod session;
+mod state;
 
 use app::LogViewer;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let directory = env::args().nth(1).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("."));
+    let args = match cli::parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("multail: {}\n\n{}", error, cli::USAGE);
+            process::exit(2);
+        }
+    };
+
+    if args.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+    if args.version {
+        println!("multail {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+
+    let config = match config::Config::load() {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("multail: {}", error);
+            process::exit(1);
+        }
+    };
+
+    // Pipelines group entries by the same rules as the viewer
+    let continuation = match continuation::Continuation::from_config(&config.multiline) {
+        Ok(continuation) => continuation,
+        Err(error) => {
+            eprintln!("multail: config multiline: {}", error);
+            process::exit(1);
+        }
+    };
+    let parser = log_parser::LogParser::with_continuation(continuation);
+
+    if args.print {
+        match pipeline::run(&args, &parser) {
+            Ok(true) => return Ok(()),
+            // The unreadable files were reported as they came up
+            Ok(false) => process::exit(1),
+            Err(error) => {
+                eprintln!("multail: {}", error);
+                process::exit(1);
+            }
+        }
+    }
+
+    // Bad bindings are reported before the terminal is taken over
+    let keymap = match keymap::Keymap::from_config(&config.keys) {
+        Ok(keymap) => keymap,
+        Err(error) => {
+            eprintln!("multail: config {}", error);
+            process::exit(1);
+        }
+    };
+
+    let directory = args.paths.first().cloned().unwrap_or_else(|| PathBuf::from("."));
     
-    let mut app = LogViewer::new(directory)?;
+    let mut app = LogViewer::new(directory, args.filter, &config, keymap, parser)?;
     app.run()?;
     
     Ok(())
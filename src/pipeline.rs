@@ -0,0 +1,290 @@
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use colored::Colorize;
+use walkdir::WalkDir;
+use crate::cli::{Args, ColorChoice};
+use crate::filter::EntryFilter;
+use crate::log_parser::{LogEntry, LogLevel, LogParser};
+use crate::query;
+
+/// Runs `multail --print`: parses the given files (or stdin) and writes the
+/// entries matching the filter to stdout, optionally following the files.
+/// `parser` carries the `[multiline]` rules from the config. Files that
+/// can't be read are reported and skipped; returns whether there were none.
+pub fn run(args: &Args, parser: &LogParser) -> io::Result<bool> {
+    let use_color = match args.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && io::stdout().is_terminal()
+        }
+    };
+    colored::control::set_override(use_color);
+
+    let result = print_all(args, parser);
+    // Being cut short by `head` or similar is not an error
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(true),
+        result => result,
+    }
+}
+
+/// Reads a file, reporting it on stderr if it can't be.
+fn read_file(file: &Path) -> Option<Vec<u8>> {
+    match fs::read(file) {
+        Ok(content) => Some(content),
+        Err(e) => {
+            eprintln!("multail: {}: {}", file.display(), e);
+            None
+        }
+    }
+}
+
+fn print_all(args: &Args, parser: &LogParser) -> io::Result<bool> {
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+
+    if let Some(sql) = &args.query {
+        return print_query(&mut out, parser, args, sql);
+    }
+
+    if args.paths.is_empty() {
+        let mut content = Vec::new();
+        io::stdin().read_to_end(&mut content)?;
+        print_entries(&mut out, &parser.parse(&content), &args.filter, args.lines)?;
+        out.flush()?;
+        return Ok(true);
+    }
+
+    let files = expand_paths(&args.paths);
+    let show_headers = files.len() > 1;
+    let mut sources = Vec::new();
+    let mut all_read = true;
+    for (i, file) in files.iter().enumerate() {
+        let Some(content) = read_file(file) else {
+            all_read = false;
+            continue;
+        };
+        // When following, a partial last line is left for the next read
+        let complete = if args.follow {
+            content.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1)
+        } else {
+            content.len()
+        };
+
+        if show_headers {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "==> {} <==", file.display())?;
+        }
+        let content = &content[..complete];
+        if args.follow {
+            // The last entry is held back while later reads may continue it
+            let mut source = Followed::new(file.clone(), complete as u64);
+            let entries = parser.parse_continuing(content, &mut source.open);
+            let held = source.open.as_ref().is_some_and(|entry| args.filter.matches(entry)) as usize;
+            print_entries(&mut out, &entries, &args.filter, args.lines.map(|n| n.saturating_sub(held)))?;
+            sources.push(source);
+        } else {
+            print_entries(&mut out, &parser.parse(content), &args.filter, args.lines)?;
+        }
+    }
+    out.flush()?;
+
+    if args.follow {
+        follow(&mut out, parser, &args.filter, sources, show_headers)?;
+    }
+    Ok(all_read)
+}
+
+/// Runs a query over the entries of all given files (or stdin) and prints
+/// the result table. Returns whether every file could be read.
+fn print_query(out: &mut impl Write, parser: &LogParser, args: &Args, sql: &str) -> io::Result<bool> {
+    let mut sources: Vec<(String, Vec<LogEntry>)> = Vec::new();
+    if args.paths.is_empty() {
+        let mut content = Vec::new();
+        io::stdin().read_to_end(&mut content)?;
+        sources.push(("-".to_string(), parser.parse(&content)));
+    }
+    let mut all_read = true;
+    for file in expand_paths(&args.paths) {
+        match read_file(&file) {
+            Some(content) => sources.push((file.display().to_string(), parser.parse(&content))),
+            None => all_read = false,
+        }
+    }
+
+    let rows: Vec<query::Row> = sources
+        .iter()
+        .flat_map(|(file, entries)| query::rows(entries, file, 1))
+        .filter(|row| args.filter.matches(row.entry))
+        .collect();
+    let result = query::run(sql, &rows).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    write!(out, "{}", result.to_table())?;
+    out.flush()?;
+    Ok(all_read)
+}
+
+/// A file being followed: how far it has been read, and its last entry,
+/// which lines in later reads may continue.
+struct Followed {
+    file: PathBuf,
+    offset: u64,
+    open: Option<LogEntry>,
+    /// When `open` was last read further.
+    changed: Instant,
+    /// Once the group timeout let `open` through: whether it matched the
+    /// filter, and how many of its lines have been printed.
+    flushed: Option<(bool, usize)>,
+}
+
+impl Followed {
+    fn new(file: PathBuf, offset: u64) -> Self {
+        Self { file, offset, open: None, changed: Instant::now(), flushed: None }
+    }
+}
+
+/// Polls the files for appended data like `tail -f`, printing a header
+/// whenever output switches to a different file. Entries are filtered once
+/// complete: when the next one starts, or when the group timeout passes
+/// without more lines. Lines continuing one after that follow it as they come.
+fn follow(
+    out: &mut impl Write,
+    parser: &LogParser,
+    filter: &EntryFilter,
+    mut sources: Vec<Followed>,
+    show_headers: bool,
+) -> io::Result<()> {
+    let mut last_printed = sources.last().map(|source| source.file.clone());
+    loop {
+        thread::sleep(Duration::from_millis(250));
+        for source in sources.iter_mut() {
+            let entries = match read_appended(source)? {
+                Some(content) => {
+                    source.changed = Instant::now();
+                    parser.parse_continuing(&content, &mut source.open)
+                }
+                None => Vec::new(),
+            };
+
+            let mut chunks: Vec<(LogLevel, &[String])> = Vec::new();
+            let mut completed = entries.as_slice();
+            if let Some((matched, printed)) = source.flushed {
+                let entry = match completed.split_first() {
+                    Some((first, rest)) => {
+                        completed = rest;
+                        source.flushed = None;
+                        Some(first)
+                    }
+                    None => {
+                        source.flushed = Some((matched, source.open.as_ref().map_or(printed, |e| e.lines.len())));
+                        source.open.as_ref()
+                    }
+                };
+                if let Some(entry) = entry.filter(|_| matched) {
+                    chunks.push((entry.level, &entry.lines[printed..]));
+                }
+            }
+            chunks.extend(completed.iter().filter(|e| filter.matches(e)).map(|e| (e.level, e.lines.as_slice())));
+            if source.flushed.is_none() && source.changed.elapsed() >= parser.group_timeout() {
+                if let Some(entry) = &source.open {
+                    let matched = filter.matches(entry);
+                    if matched {
+                        chunks.push((entry.level, &entry.lines));
+                    }
+                    source.flushed = Some((matched, entry.lines.len()));
+                }
+            }
+
+            if chunks.iter().all(|(_, lines)| lines.is_empty()) {
+                continue;
+            }
+            if show_headers && last_printed.as_ref() != Some(&source.file) {
+                writeln!(out, "\n==> {} <==", source.file.display())?;
+                last_printed = Some(source.file.clone());
+            }
+            for (level, lines) in chunks {
+                print_lines(out, level, lines)?;
+            }
+        }
+        out.flush()?;
+    }
+}
+
+/// Reads the complete lines appended to a followed file since last time.
+fn read_appended(source: &mut Followed) -> io::Result<Option<Vec<u8>>> {
+    let Ok(size) = source.file.metadata().map(|m| m.len()) else {
+        return Ok(None);
+    };
+    if size < source.offset {
+        // Truncated or rotated in place; start over from the beginning
+        *source = Followed::new(source.file.clone(), 0);
+    }
+    if size == source.offset {
+        return Ok(None);
+    }
+
+    let mut handle = File::open(&source.file)?;
+    handle.seek(io::SeekFrom::Start(source.offset))?;
+    let mut content = Vec::new();
+    handle.take(size - source.offset).read_to_end(&mut content)?;
+    let Some(end) = content.iter().rposition(|&b| b == b'\n') else {
+        return Ok(None);
+    };
+    source.offset += end as u64 + 1;
+    content.truncate(end + 1);
+    Ok(Some(content))
+}
+
+fn print_entries(
+    out: &mut impl Write,
+    entries: &[LogEntry],
+    filter: &EntryFilter,
+    last: Option<usize>,
+) -> io::Result<()> {
+    let matching: Vec<&LogEntry> = entries.iter().filter(|e| filter.matches(e)).collect();
+    let skip = last.map_or(0, |n| matching.len().saturating_sub(n));
+    for entry in &matching[skip..] {
+        print_lines(out, entry.level, &entry.lines)?;
+    }
+    Ok(())
+}
+
+/// Writes lines of an entry, coloured by its level.
+fn print_lines(out: &mut impl Write, level: LogLevel, lines: &[String]) -> io::Result<()> {
+    for line in lines {
+        let line = match level {
+            LogLevel::Debug => line.bright_black(),
+            LogLevel::Info => line.normal(),
+            LogLevel::Warn => line.yellow(),
+            LogLevel::Error => line.red(),
+        };
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// Expands directories into the files they contain, in a stable order.
+fn expand_paths(paths: &[PathBuf]) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .flat_map(|path| {
+            if path.is_dir() {
+                let mut files: Vec<PathBuf> = WalkDir::new(path)
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().is_file())
+                    .map(|e| e.into_path())
+                    .collect();
+                files.sort();
+                files
+            } else {
+                vec![path.to_path_buf()]
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,838 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use regex::Regex;
+use crate::fields;
+use crate::log_parser::LogEntry;
+
+/// The columns `SELECT *` expands to. Any other column name is looked up
+/// in the entry's extracted fields (JSON keys or logfmt pairs).
+const STANDARD_COLUMNS: [&str; 4] = ["line", "time", "level", "msg"];
+
+/// A log entry as seen by a query, with the context that isn't stored on the entry.
+pub struct Row<'a> {
+    pub entry: &'a LogEntry,
+    pub file: &'a str,
+    pub line: usize,
+}
+
+/// Builds query rows for the entries of one file, numbering their first
+/// lines from `first_line`, the file line the entries start at.
+pub fn rows<'a>(entries: &'a [LogEntry], file: &'a str, first_line: usize) -> Vec<Row<'a>> {
+    let mut line = first_line;
+    entries
+        .iter()
+        .map(|entry| {
+            let row = Row { entry, file, line };
+            line += entry.lines.len();
+            row
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Number(f64),
+    Text(String),
+}
+
+impl Value {
+    fn from_text(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Text(s) => fields::numeric(s),
+            Value::Null => None,
+        }
+    }
+
+    fn is_truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Number(n) => *n != 0.0,
+            Value::Text(s) => !s.is_empty(),
+        }
+    }
+
+    /// Orders NULL first, then as `fields::compare_values`: numbers before
+    /// text, so that sorting never sees an inconsistent order.
+    fn compare(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Less,
+            (_, Value::Null) => Ordering::Greater,
+            _ => {
+                let number = |v: &Value| v.as_number().filter(|n| !n.is_nan());
+                fields::compare_values(number(self), &self.to_string(), number(other), &other.to_string())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Text(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl QueryResult {
+    /// Formats the result as an aligned plain-text table.
+    pub fn to_table(&self) -> String {
+        let cells: Vec<Vec<String>> = self.rows
+            .iter()
+            .map(|row| row.iter().map(|v| v.to_string().replace('\n', " ")).collect())
+            .collect();
+        let widths: Vec<usize> = self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| cells.iter().map(|r| r[i].chars().count()).chain([c.len()]).max().unwrap_or(0))
+            .collect();
+
+        let format_row = |row: &[String]| {
+            let padded: Vec<String> = row.iter().zip(&widths).map(|(c, &w)| format!("{:<w$}", c, w = w)).collect();
+            padded.join("  ").trim_end().to_string()
+        };
+        let mut out = format_row(&self.columns) + "\n";
+        for row in &cells {
+            out += &format_row(row);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// State of the query result overlay.
+pub struct QueryView {
+    pub sql: String,
+    pub result: QueryResult,
+    pub selected: usize,
+}
+
+impl QueryView {
+    pub fn new(sql: String, result: QueryResult) -> Self {
+        Self { sql, result, selected: 0 }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.result.rows.len() {
+            self.selected += 1;
+        }
+    }
+}
+
+/// Parses and runs a query over the given rows.
+pub fn run(sql: &str, rows: &[Row]) -> Result<QueryResult, String> {
+    let query = Parser::new(tokenize(sql)?).parse_query()?;
+    query.execute(rows)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Symbol(&'static str),
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Token>, String> {
+    const SYMBOLS: [&str; 12] = ["<=", ">=", "!=", "<>", "=", "<", ">", "(", ")", ",", "*", ";"];
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '\'' || c == '"' {
+            // Quotes are escaped by doubling them, as in SQL
+            let mut s = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err("unterminated string".to_string()),
+                    Some(&q) if q == c && chars.get(i + 1) == Some(&c) => {
+                        s.push(c);
+                        i += 2;
+                    }
+                    Some(&q) if q == c => break,
+                    Some(&other) => {
+                        s.push(other);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            // Double quotes delimit identifiers, e.g. field names with dots
+            tokens.push(if c == '"' { Token::Ident(s) } else { Token::Str(s) });
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(Token::Num(text.parse().map_err(|_| format!("invalid number '{}'", text))?));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.')) {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let symbol = SYMBOLS
+                .iter()
+                .find(|s| rest.starts_with(**s))
+                .ok_or_else(|| format!("unexpected character '{}'", c))?;
+            tokens.push(Token::Symbol(symbol));
+            i += symbol.len();
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Column(String),
+    Literal(Value),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, &'static str, Box<Expr>),
+    Like { expr: Box<Expr>, pattern: Box<Expr>, regexp: bool, negated: bool },
+    IsNull { expr: Box<Expr>, negated: bool },
+    Call { name: String, args: Vec<Expr> },
+    CountStar,
+}
+
+impl Expr {
+    fn is_aggregate(&self) -> bool {
+        match self {
+            Expr::CountStar => true,
+            Expr::Call { name, args } => {
+                matches!(name.as_str(), "count" | "min" | "max" | "sum" | "avg") || args.iter().any(Expr::is_aggregate)
+            }
+            Expr::Not(e) | Expr::IsNull { expr: e, .. } => e.is_aggregate(),
+            Expr::And(a, b) | Expr::Or(a, b) | Expr::Compare(a, _, b) => a.is_aggregate() || b.is_aggregate(),
+            Expr::Like { expr, pattern, .. } => expr.is_aggregate() || pattern.is_aggregate(),
+            Expr::Column(_) | Expr::Literal(_) => false,
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Expr::Column(name) => name.clone(),
+            Expr::CountStar => "count(*)".to_string(),
+            Expr::Call { name, args } => {
+                format!("{}({})", name, args.iter().map(Expr::name).collect::<Vec<_>>().join(", "))
+            }
+            Expr::Literal(value) => value.to_string(),
+            _ => "expr".to_string(),
+        }
+    }
+}
+
+struct SelectItem {
+    expr: Expr,
+    alias: String,
+}
+
+struct Query {
+    select: Vec<SelectItem>,
+    filter: Option<Expr>,
+    group_by: Vec<Expr>,
+    order_by: Vec<(Expr, bool)>,
+    limit: Option<usize>,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(s)) if s.eq_ignore_ascii_case(keyword))
+    }
+
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if self.accept_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(format!("expected {}", keyword.to_uppercase()))
+        }
+    }
+
+    fn accept_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        if self.accept_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(format!("expected '{}'", symbol))
+        }
+    }
+
+    fn parse_query(&mut self) -> Result<Query, String> {
+        self.expect_keyword("select")?;
+        let mut select = Vec::new();
+        loop {
+            if self.accept_symbol("*") {
+                select.extend(STANDARD_COLUMNS.iter().map(|c| SelectItem {
+                    expr: Expr::Column(c.to_string()),
+                    alias: c.to_string(),
+                }));
+            } else {
+                let expr = self.parse_expr()?;
+                let alias = if self.accept_keyword("as") {
+                    self.parse_ident()?
+                } else {
+                    expr.name()
+                };
+                select.push(SelectItem { expr, alias });
+            }
+            if !self.accept_symbol(",") {
+                break;
+            }
+        }
+
+        self.expect_keyword("from")?;
+        let table = self.parse_ident()?;
+        if !table.eq_ignore_ascii_case("logs") {
+            return Err(format!("unknown table '{}', expected 'logs'", table));
+        }
+
+        let filter = if self.accept_keyword("where") { Some(self.parse_expr()?) } else { None };
+
+        let mut group_by = Vec::new();
+        if self.accept_keyword("group") {
+            self.expect_keyword("by")?;
+            loop {
+                group_by.push(self.parse_expr()?);
+                if !self.accept_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        let mut order_by = Vec::new();
+        if self.accept_keyword("order") {
+            self.expect_keyword("by")?;
+            loop {
+                let expr = self.parse_expr()?;
+                let descending = if self.accept_keyword("desc") {
+                    true
+                } else {
+                    self.accept_keyword("asc");
+                    false
+                };
+                order_by.push((expr, descending));
+                if !self.accept_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        let limit = if self.accept_keyword("limit") {
+            match self.tokens.get(self.pos) {
+                Some(Token::Num(n)) if *n >= 0.0 => {
+                    self.pos += 1;
+                    Some(*n as usize)
+                }
+                _ => return Err("expected a number after LIMIT".to_string()),
+            }
+        } else {
+            None
+        };
+
+        self.accept_symbol(";");
+        if let Some(token) = self.peek() {
+            return Err(format!("unexpected {:?} at end of query", token));
+        }
+        Ok(Query { select, filter, group_by, order_by, limit })
+    }
+
+    fn parse_ident(&mut self) -> Result<String, String> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                Ok(name.clone())
+            }
+            _ => Err("expected a name".to_string()),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_and()?;
+        while self.accept_keyword("or") {
+            left = Expr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_not()?;
+        while self.accept_keyword("and") {
+            left = Expr::And(Box::new(left), Box::new(self.parse_not()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, String> {
+        if self.accept_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, String> {
+        let left = self.parse_primary()?;
+        for op in ["=", "!=", "<>", "<=", ">=", "<", ">"] {
+            if self.accept_symbol(op) {
+                let op = if op == "<>" { "!=" } else { op };
+                return Ok(Expr::Compare(Box::new(left), op, Box::new(self.parse_primary()?)));
+            }
+        }
+
+        if self.accept_keyword("is") {
+            let negated = self.accept_keyword("not");
+            self.expect_keyword("null")?;
+            return Ok(Expr::IsNull { expr: Box::new(left), negated });
+        }
+
+        let negated = self.peek_keyword("not")
+            && matches!(self.tokens.get(self.pos + 1), Some(Token::Ident(s)) if s.eq_ignore_ascii_case("like") || s.eq_ignore_ascii_case("regexp"));
+        if negated {
+            self.pos += 1;
+        }
+        for (keyword, regexp) in [("like", false), ("regexp", true)] {
+            if self.accept_keyword(keyword) {
+                let pattern = Box::new(self.parse_primary()?);
+                return Ok(Expr::Like { expr: Box::new(left), pattern, regexp, negated });
+            }
+        }
+        Ok(left)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.tokens.get(self.pos).cloned() {
+            Some(Token::Num(n)) => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Number(n)))
+            }
+            Some(Token::Str(s)) => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Text(s)))
+            }
+            Some(Token::Symbol("(")) => {
+                self.pos += 1;
+                let expr = self.parse_expr()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                if name.eq_ignore_ascii_case("null") {
+                    return Ok(Expr::Literal(Value::Null));
+                }
+                if !self.accept_symbol("(") {
+                    return Ok(Expr::Column(name));
+                }
+                let name = name.to_ascii_lowercase();
+                if name == "count" && self.accept_symbol("*") {
+                    self.expect_symbol(")")?;
+                    return Ok(Expr::CountStar);
+                }
+                let mut args = Vec::new();
+                if !self.accept_symbol(")") {
+                    loop {
+                        args.push(self.parse_expr()?);
+                        if !self.accept_symbol(",") {
+                            break;
+                        }
+                    }
+                    self.expect_symbol(")")?;
+                }
+                Ok(Expr::Call { name, args })
+            }
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of query".to_string()),
+        }
+    }
+}
+
+/// Evaluation context for one row; extracted fields are computed on first use.
+struct RowContext<'a, 'r> {
+    row: &'r Row<'a>,
+    fields: Option<Vec<(String, String)>>,
+}
+
+impl RowContext<'_, '_> {
+    fn column(&mut self, name: &str) -> Value {
+        let entry = self.row.entry;
+        match name.to_ascii_lowercase().as_str() {
+            "line" => return Value::Number(self.row.line as f64),
+            "time" | "timestamp" if entry.timestamp.is_empty() => return Value::Null,
+            "time" | "timestamp" => return Value::from_text(&entry.timestamp),
+            "level" => return Value::from_text(entry.level.name()),
+            "msg" | "message" => return Value::from_text(&entry.message),
+            "text" | "raw" => return Value::Text(entry.lines.join("\n")),
+            "lines" => return Value::Number(entry.lines.len() as f64),
+            "file" => return Value::from_text(self.row.file),
+            _ => {}
+        }
+        let fields = self.fields.get_or_insert_with(|| fields::extract_fields(&entry.message));
+        fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map_or(Value::Null, |(_, value)| Value::from_text(value))
+    }
+}
+
+impl Query {
+    fn execute(&self, rows: &[Row]) -> Result<QueryResult, String> {
+        let mut patterns = HashMap::new();
+        let mut matching: Vec<RowContext> = Vec::new();
+        for row in rows {
+            let mut context = RowContext { row, fields: None };
+            let keep = match &self.filter {
+                Some(filter) => eval(filter, &mut context, &mut patterns)?.is_truthy(),
+                None => true,
+            };
+            if keep {
+                matching.push(context);
+            }
+        }
+
+        let columns: Vec<String> = self.select.iter().map(|item| item.alias.clone()).collect();
+        let grouped = !self.group_by.is_empty() || self.select.iter().any(|item| item.expr.is_aggregate());
+
+        // Each output row carries its sort keys, evaluated against the source rows
+        let mut output: Vec<(Vec<Value>, Vec<Value>)> = Vec::new();
+        if grouped {
+            let mut groups: Vec<(Vec<Value>, Vec<usize>)> = Vec::new();
+            let mut group_index: HashMap<String, usize> = HashMap::new();
+            for (i, context) in matching.iter_mut().enumerate() {
+                let key: Vec<Value> = self.group_by
+                    .iter()
+                    .map(|e| eval(e, context, &mut patterns))
+                    .collect::<Result<_, _>>()?;
+                let key_text = format!("{:?}", key);
+                let index = *group_index.entry(key_text).or_insert_with(|| {
+                    groups.push((key, Vec::new()));
+                    groups.len() - 1
+                });
+                groups[index].1.push(i);
+            }
+            if groups.is_empty() && self.group_by.is_empty() {
+                // Aggregates over no rows still produce one row, e.g. count(*) = 0
+                groups.push((Vec::new(), Vec::new()));
+            }
+
+            for (_, members) in &groups {
+                let mut values = Vec::new();
+                for item in &self.select {
+                    values.push(eval_aggregate(&item.expr, &mut matching, members, &mut patterns)?);
+                }
+                let mut keys = Vec::new();
+                for (expr, _) in &self.order_by {
+                    keys.push(match self.output_column(expr) {
+                        Some(i) => values[i].clone(),
+                        None => eval_aggregate(expr, &mut matching, members, &mut patterns)?,
+                    });
+                }
+                output.push((values, keys));
+            }
+        } else {
+            for context in matching.iter_mut() {
+                let mut values = Vec::new();
+                for item in &self.select {
+                    values.push(eval(&item.expr, context, &mut patterns)?);
+                }
+                let mut keys = Vec::new();
+                for (expr, _) in &self.order_by {
+                    keys.push(match self.output_column(expr) {
+                        Some(i) => values[i].clone(),
+                        None => eval(expr, context, &mut patterns)?,
+                    });
+                }
+                output.push((values, keys));
+            }
+        }
+
+        if !self.order_by.is_empty() {
+            output.sort_by(|(_, a), (_, b)| {
+                self.order_by
+                    .iter()
+                    .zip(a.iter().zip(b))
+                    .map(|((_, descending), (a, b))| if *descending { b.compare(a) } else { a.compare(b) })
+                    .find(|ordering| *ordering != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+        if let Some(limit) = self.limit {
+            output.truncate(limit);
+        }
+
+        Ok(QueryResult { columns, rows: output.into_iter().map(|(values, _)| values).collect() })
+    }
+
+    /// Resolves ORDER BY terms naming a selected column or alias.
+    fn output_column(&self, expr: &Expr) -> Option<usize> {
+        match expr {
+            Expr::Column(name) => self.select.iter().position(|item| item.alias.eq_ignore_ascii_case(name)),
+            Expr::Literal(Value::Number(n)) if *n >= 1.0 && (*n as usize) <= self.select.len() => Some(*n as usize - 1),
+            _ => None,
+        }
+    }
+}
+
+type Patterns = HashMap<(String, bool), Regex>;
+
+fn eval(expr: &Expr, context: &mut RowContext, patterns: &mut Patterns) -> Result<Value, String> {
+    let boolean = |b: bool| Value::Number(if b { 1.0 } else { 0.0 });
+    Ok(match expr {
+        Expr::Column(name) => context.column(name),
+        Expr::Literal(value) => value.clone(),
+        Expr::Not(e) => boolean(!eval(e, context, patterns)?.is_truthy()),
+        Expr::And(a, b) => boolean(eval(a, context, patterns)?.is_truthy() && eval(b, context, patterns)?.is_truthy()),
+        Expr::Or(a, b) => boolean(eval(a, context, patterns)?.is_truthy() || eval(b, context, patterns)?.is_truthy()),
+        Expr::Compare(a, op, b) => {
+            let (a, b) = (eval(a, context, patterns)?, eval(b, context, patterns)?);
+            if a == Value::Null || b == Value::Null {
+                return Ok(Value::Null);
+            }
+            let ordering = a.compare(&b);
+            boolean(match *op {
+                "=" => ordering == Ordering::Equal,
+                "!=" => ordering != Ordering::Equal,
+                "<" => ordering == Ordering::Less,
+                "<=" => ordering != Ordering::Greater,
+                ">" => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            })
+        }
+        Expr::Like { expr, pattern, regexp, negated } => {
+            let value = eval(expr, context, patterns)?;
+            let pattern = eval(pattern, context, patterns)?.to_string();
+            if value == Value::Null {
+                return Ok(Value::Null);
+            }
+            let regex = match patterns.entry((pattern.clone(), *regexp)) {
+                std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+                std::collections::hash_map::Entry::Vacant(e) => {
+                    let source = if *regexp { pattern } else { like_to_regex(&pattern) };
+                    e.insert(Regex::new(&source).map_err(|e| format!("invalid pattern: {}", e))?)
+                }
+            };
+            boolean(regex.is_match(&value.to_string()) != *negated)
+        }
+        Expr::IsNull { expr, negated } => boolean((eval(expr, context, patterns)? == Value::Null) != *negated),
+        Expr::Call { name, args } => {
+            let args: Vec<Value> = args.iter().map(|a| eval(a, context, patterns)).collect::<Result<_, _>>()?;
+            call_scalar(name, &args)?
+        }
+        Expr::CountStar => return Err("count(*) is only allowed in the select list".to_string()),
+    })
+}
+
+fn eval_aggregate(
+    expr: &Expr,
+    rows: &mut [RowContext],
+    members: &[usize],
+    patterns: &mut Patterns,
+) -> Result<Value, String> {
+    match expr {
+        Expr::CountStar => Ok(Value::Number(members.len() as f64)),
+        Expr::Call { name, args } if matches!(name.as_str(), "count" | "min" | "max" | "sum" | "avg") => {
+            let [arg] = args.as_slice() else {
+                return Err(format!("{}() takes one argument", name));
+            };
+            let mut values = Vec::new();
+            for &i in members {
+                let value = eval(arg, &mut rows[i], patterns)?;
+                if value != Value::Null {
+                    values.push(value);
+                }
+            }
+            let numbers = || values.iter().filter_map(Value::as_number);
+            Ok(match name.as_str() {
+                "count" => Value::Number(values.len() as f64),
+                "min" => values.iter().min_by(|a, b| a.compare(b)).cloned().unwrap_or(Value::Null),
+                "max" => values.iter().max_by(|a, b| a.compare(b)).cloned().unwrap_or(Value::Null),
+                "sum" => Value::Number(numbers().sum()),
+                _ => match numbers().count() {
+                    0 => Value::Null,
+                    n => Value::Number(numbers().sum::<f64>() / n as f64),
+                },
+            })
+        }
+        // Anything else is evaluated against the first row of the group
+        _ if !expr.is_aggregate() => match members.first() {
+            Some(&i) => eval(expr, &mut rows[i], patterns),
+            None => Ok(Value::Null),
+        },
+        _ => Err(format!("cannot mix aggregates in '{}'", expr.name())),
+    }
+}
+
+fn call_scalar(name: &str, args: &[Value]) -> Result<Value, String> {
+    match (name, args) {
+        ("lower", [v]) => Ok(Value::Text(v.to_string().to_lowercase())),
+        ("upper", [v]) => Ok(Value::Text(v.to_string().to_uppercase())),
+        ("length", [v]) => Ok(Value::Number(v.to_string().chars().count() as f64)),
+        ("lower" | "upper" | "length", _) => Err(format!("{}() takes one argument", name)),
+        _ => Err(format!("unknown function '{}'", name)),
+    }
+}
+
+/// Converts a SQL LIKE pattern (`%` and `_` wildcards) into a
+/// case-insensitive anchored regex, as SQLite does for ASCII.
+fn like_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("(?is)^");
+    for c in pattern.chars() {
+        match c {
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuation::Continuation;
+    use crate::log_parser::LogParser;
+
+    const LOG: &str = "\
+[2024-05-01 10:00:00] [INFO] request path=/a took=12 user=ann
+[2024-05-01 10:00:01] [ERROR] request path=/b took=5 user=bob
+[2024-05-01 10:00:02] [INFO] request path=/a took=30
+[2024-05-01 10:00:03] [WARN] request path=/c took=slow user=ann
+[2024-05-01 10:00:04] [INFO] request path=/a took=7 user=bob
+";
+
+    fn query(sql: &str) -> Result<Vec<Vec<String>>, String> {
+        let entries = LogParser::with_continuation(Continuation::default()).parse(LOG.as_bytes());
+        let result = run(sql, &rows(&entries, "app.log", 1))?;
+        Ok(result.rows.iter().map(|row| row.iter().map(Value::to_string).collect()).collect())
+    }
+
+    fn column(sql: &str) -> Vec<String> {
+        query(sql).unwrap().into_iter().map(|mut row| row.remove(0)).collect()
+    }
+
+    #[test]
+    fn tokenizes_quoted_strings_and_identifiers() {
+        let tokens = tokenize(r#"SELECT "http.path", 'it''s' FROM logs WHERE took>=1.5;"#).unwrap();
+        assert_eq!(tokens, vec![
+            Token::Ident("SELECT".to_string()),
+            Token::Ident("http.path".to_string()),
+            Token::Symbol(","),
+            Token::Str("it's".to_string()),
+            Token::Ident("FROM".to_string()),
+            Token::Ident("logs".to_string()),
+            Token::Ident("WHERE".to_string()),
+            Token::Ident("took".to_string()),
+            Token::Symbol(">="),
+            Token::Num(1.5),
+            Token::Symbol(";"),
+        ]);
+        assert!(tokenize("SELECT 'open").is_err());
+        assert!(tokenize("SELECT # FROM logs").is_err());
+    }
+
+    #[test]
+    fn where_matches_like_and_skips_nulls() {
+        assert_eq!(column("SELECT line FROM logs WHERE path LIKE '/A'"), ["1", "3", "5"]);
+        assert_eq!(column("SELECT line FROM logs WHERE msg NOT LIKE '%user=%'"), ["3"]);
+        assert_eq!(column("SELECT line FROM logs WHERE user IS NULL"), ["3"]);
+        // Comparing with NULL is neither true nor false, so the row is dropped either way
+        assert_eq!(column("SELECT line FROM logs WHERE user = 'ann'"), ["1", "4"]);
+        assert_eq!(column("SELECT line FROM logs WHERE user != 'ann'"), ["2", "5"]);
+        assert_eq!(column("SELECT line FROM logs WHERE took > 6 AND level = 'INFO'"), ["1", "3", "5"]);
+    }
+
+    #[test]
+    fn group_by_computes_aggregates() {
+        let sql = "SELECT path, count(*), sum(took), avg(took), min(took), max(took) FROM logs GROUP BY path ORDER BY path";
+        let rows = query(sql).unwrap();
+        assert_eq!(rows, vec![
+            vec!["/a", "3", "49", "16.333333333333332", "7", "30"],
+            vec!["/b", "1", "5", "5", "5", "5"],
+            vec!["/c", "1", "0", "", "slow", "slow"],
+        ]);
+        assert_eq!(query("SELECT count(user), count(*) FROM logs WHERE path = '/z'").unwrap(), vec![vec!["0", "0"]]);
+    }
+
+    #[test]
+    fn order_by_sorts_both_ways_and_limits() {
+        assert_eq!(column("SELECT took FROM logs ORDER BY took"), ["5", "7", "12", "30", "slow"]);
+        assert_eq!(column("SELECT took FROM logs ORDER BY took DESC LIMIT 2"), ["slow", "30"]);
+        assert_eq!(column("SELECT user AS u FROM logs ORDER BY u ASC, line DESC"), ["", "ann", "ann", "bob", "bob"]);
+        assert_eq!(column("SELECT line FROM logs ORDER BY user, line DESC LIMIT 3"), ["3", "4", "1"]);
+    }
+
+    #[test]
+    fn mixed_values_sort_without_panicking() {
+        let log: String = (0..3000)
+            .map(|i| format!("[2024-05-01 10:00:00] [INFO] v={}\n", ["10", "5a", "9", "NaN", "x", "-1"][i % 6]))
+            .collect();
+        let entries = LogParser::with_continuation(Continuation::default()).parse(log.as_bytes());
+        let result = run("SELECT v, count(*) FROM logs GROUP BY v ORDER BY v", &rows(&entries, "mix.log", 1)).unwrap();
+        let values: Vec<String> = result.rows.iter().map(|row| row[0].to_string()).collect();
+        assert_eq!(values, ["-1", "9", "10", "5a", "NaN", "x"]);
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        assert_eq!(query("SELECT").unwrap_err(), "unexpected end of query");
+        assert_eq!(query("SELECT line FROM events").unwrap_err(), "unknown table 'events', expected 'logs'");
+        assert_eq!(query("SELECT line logs").unwrap_err(), "expected FROM");
+        assert_eq!(query("SELECT line FROM logs LIMIT x").unwrap_err(), "expected a number after LIMIT");
+        assert_eq!(query("SELECT (line FROM logs").unwrap_err(), "expected ')'");
+        assert_eq!(query("SELECT line FROM logs ORDER line").unwrap_err(), "expected BY");
+        assert_eq!(query("SELECT nope(line) FROM logs").unwrap_err(), "unknown function 'nope'");
+    }
+}
@@ -0,0 +1,174 @@
+use std::fs;
+use std::io::{self, Read, Seek};
+use std::mem;
+use std::path::Path;
+use crate::config::RetentionConfig;
+use crate::log_parser::{LogEntry, LogParser};
+
+/// How much of the file before the resident entries is re-read at a time
+/// when scrolling back to evicted entries.
+const RELOAD_BYTES: u64 = 256 * 1024;
+
+/// Once over a limit, entries are evicted until this fraction of it is
+/// used, so eviction happens in chunks rather than on every append.
+const RETAIN_FRACTION: f64 = 0.9;
+
+/// Limits on how much of one source is kept in memory. Unlimited by default.
+#[derive(Clone, Copy, Default)]
+pub struct Retention {
+    max_entries: Option<usize>,
+    max_bytes: Option<u64>,
+}
+
+impl Retention {
+    /// The limits for `file`: its entry in `[retention.files]`, by file
+    /// name, with anything it leaves out taken from `[retention]`.
+    pub fn for_file(config: &RetentionConfig, file: &Path) -> Self {
+        let name = file.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let limits = config.files.get(&name);
+        Self {
+            max_entries: limits.and_then(|limits| limits.max_entries).or(config.max_entries),
+            max_bytes: limits.and_then(|limits| limits.max_mib).or(config.max_mib).map(|mib| mib * 1024 * 1024),
+        }
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.max_entries.is_some() || self.max_bytes.is_some()
+    }
+
+    /// How many of the oldest entries to evict: none while within the
+    /// limits, otherwise enough to fall back to `RETAIN_FRACTION` of them.
+    /// The last entry always stays, as later lines may still continue it.
+    pub fn excess(&self, entries: &[LogEntry], bytes: u64) -> usize {
+        let over_entries = self.max_entries.is_some_and(|max| entries.len() > max);
+        let over_bytes = self.max_bytes.is_some_and(|max| bytes > max);
+        if !over_entries && !over_bytes {
+            return 0;
+        }
+        let keep_entries = self.max_entries.map_or(usize::MAX, |max| (max as f64 * RETAIN_FRACTION) as usize);
+        let keep_bytes = self.max_bytes.map_or(u64::MAX, |max| (max as f64 * RETAIN_FRACTION) as u64);
+        let mut count = entries.len().saturating_sub(keep_entries);
+        let mut resident = bytes - entries[..count].iter().map(entry_size).sum::<u64>();
+        let most = entries.len().saturating_sub(1);
+        count = count.min(most);
+        while resident > keep_bytes && count < most {
+            resident -= entry_size(&entries[count]);
+            count += 1;
+        }
+        count
+    }
+}
+
+/// Approximate memory an entry takes up.
+pub fn entry_size(entry: &LogEntry) -> u64 {
+    let text: usize = entry.lines.iter().map(|line| line.len() + mem::size_of::<String>()).sum();
+    (mem::size_of::<LogEntry>() + entry.timestamp.len() + entry.message.len() + text) as u64
+}
+
+/// The part of a source dropped from memory: the entries before the first
+/// resident one, the file lines they span and their length in bytes.
+#[derive(Clone, Copy, Default)]
+pub struct Evicted {
+    pub entries: usize,
+    pub lines: usize,
+    pub bytes: u64,
+}
+
+impl Evicted {
+    /// Adds entries evicted after the ones already counted.
+    pub fn add(&mut self, entries: &[LogEntry]) {
+        self.entries += entries.len();
+        self.lines += entries.iter().map(|entry| entry.lines.len()).sum::<usize>();
+        self.bytes += entries.iter().map(|entry| entry.bytes).sum::<u64>();
+    }
+
+    /// 1-based file line of the first resident entry.
+    pub fn first_line(&self) -> usize {
+        self.lines + 1
+    }
+}
+
+/// Re-reads the evicted entries just before the resident ones, up to
+/// `RELOAD_BYTES` of them, and returns them with what remains evicted.
+pub fn reload(path: &Path, evicted: &Evicted, parser: &LogParser) -> io::Result<(Vec<LogEntry>, Evicted)> {
+    let mut file = fs::File::open(path)?;
+    if file.metadata()?.len() < evicted.bytes {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the file has been truncated"));
+    }
+    let mut start = evicted.bytes.saturating_sub(RELOAD_BYTES);
+    file.seek(io::SeekFrom::Start(start))?;
+    let mut buffer = vec![0; (evicted.bytes - start) as usize];
+    file.read_exact(&mut buffer)?;
+
+    // Start at a line boundary, unless reading from the start of the file
+    if start > 0 {
+        let line_start = buffer.iter().position(|&b| b == b'\n').map_or(0, |i| i + 1);
+        buffer.drain(..line_start);
+        start += line_start as u64;
+    }
+    let mut entries = parser.parse(&buffer);
+
+    // Lines before the first entry header continue an entry further back,
+    // which the next reload reads in full
+    if start > 0 {
+        let first = entries.iter().position(|entry| !entry.timestamp.is_empty()).unwrap_or(0);
+        for entry in entries.drain(..first) {
+            start += entry.bytes;
+        }
+    }
+
+    let lines: usize = entries.iter().map(|entry| entry.lines.len()).sum();
+    let remaining = Evicted {
+        entries: if start == 0 { 0 } else { evicted.entries.saturating_sub(entries.len()) },
+        lines: if start == 0 { 0 } else { evicted.lines.saturating_sub(lines) },
+        bytes: start,
+    };
+    Ok((entries, remaining))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuation::Continuation;
+
+    /// Evicts the first `count` entries of `content` and reloads them,
+    /// expecting the same entries back.
+    fn evict_and_reload(name: &str, content: &[u8], count: usize) {
+        let path = std::env::temp_dir().join(format!("multail-retention-{}-{}.log", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        let parser = LogParser::with_continuation(Continuation::default());
+        let entries = parser.parse(content);
+        let mut evicted = Evicted::default();
+        evicted.add(&entries[..count]);
+
+        let (reloaded, remaining) = reload(&path, &evicted, &parser).unwrap();
+        fs::remove_file(&path).unwrap();
+        let lines = |entries: &[LogEntry]| entries.iter().map(|entry| entry.lines.clone()).collect::<Vec<_>>();
+        assert_eq!(lines(&reloaded), lines(&entries[..count]));
+        assert_eq!(remaining.bytes, 0);
+        assert_eq!(remaining.entries, 0);
+    }
+
+    #[test]
+    fn reload_after_evicting_crlf_entries() {
+        let content = b"[2024-05-01 10:00:00] [INFO] one\r\n[2024-05-01 10:00:01] [ERROR] two\r\n    at foo\r\n[2024-05-01 10:00:02] [INFO] three\r\n";
+        evict_and_reload("crlf", content, 2);
+    }
+
+    #[test]
+    fn reload_after_evicting_invalid_utf8() {
+        let content = b"[2024-05-01 10:00:00] [INFO] caf\xe9\n[2024-05-01 10:00:01] [INFO] \xff\xfe two\n[2024-05-01 10:00:02] [INFO] three\n";
+        evict_and_reload("utf8", content, 2);
+    }
+
+    #[test]
+    fn the_last_entry_is_never_evicted() {
+        let parser = LogParser::with_continuation(Continuation::default());
+        let entries = parser.parse(b"[2024-05-01 10:00:00] [INFO] one\n[2024-05-01 10:00:01] [ERROR] two\n    at foo\n");
+        let bytes = entries.iter().map(entry_size).sum();
+        for retention in [Retention { max_entries: Some(0), max_bytes: None }, Retention { max_entries: None, max_bytes: Some(1) }] {
+            assert_eq!(retention.excess(&entries, bytes), 1);
+            assert_eq!(retention.excess(&entries[1..], entry_size(&entries[1])), 0);
+        }
+    }
+}
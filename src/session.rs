@@ -0,0 +1,24 @@
+use std::io;
+use serde::{Deserialize, Serialize};
+use crate::state;
+
+/// View settings that carry over to the next run, kept in `session.json`.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub file_list_width: Option<u16>,
+    pub file_list_hidden: bool,
+    pub auto_expand_errors: bool,
+}
+
+impl Session {
+    const FILE: &'static str = "session.json";
+
+    pub fn load() -> Self {
+        state::load_json(Self::FILE)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        state::save_json(Self::FILE, self)
+    }
+}
@@ -0,0 +1,43 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Where state that carries over between runs is kept:
+/// `$XDG_STATE_HOME/multail`, or `~/.local/state/multail`.
+pub fn dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))?;
+    Some(base.join("multail"))
+}
+
+/// Identifies a file in per-file state by its canonical path.
+pub fn file_key(file: &Path) -> String {
+    fs::canonicalize(file)
+        .unwrap_or_else(|_| file.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Reads the JSON state file `name`. One that is missing or unreadable
+/// gives the default, so bad state never keeps the viewer from starting.
+pub fn load_json<T: DeserializeOwned + Default>(name: &str) -> T {
+    dir()
+        .and_then(|dir| fs::read_to_string(dir.join(name)).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Writes the JSON state file `name`, creating the state directory if
+/// needed. Without a state directory nothing is saved.
+pub fn save_json<T: Serialize>(name: &str, value: &T) -> io::Result<()> {
+    let Some(dir) = dir() else {
+        return Ok(());
+    };
+    fs::create_dir_all(&dir)?;
+    let content = serde_json::to_string_pretty(value).map_err(io::Error::other)?;
+    fs::write(dir.join(name), content)
+}
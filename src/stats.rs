@@ -0,0 +1,157 @@
+use std::collections::{BTreeMap, HashMap};
+use chrono::NaiveDateTime;
+use regex::Regex;
+use crate::log_parser::{LogEntry, LogLevel};
+
+/// Running statistics over the loaded log entries, updated incrementally
+/// as entries are appended so the statistics panel stays live while tailing.
+pub struct LogStats {
+    pub total: usize,
+    level_counts: HashMap<LogLevel, usize>,
+    per_second: BTreeMap<i64, u64>,
+    messages: HashMap<String, usize>,
+    number_regex: Regex,
+}
+
+impl LogStats {
+    pub fn new() -> Self {
+        Self {
+            total: 0,
+            level_counts: HashMap::new(),
+            per_second: BTreeMap::new(),
+            messages: HashMap::new(),
+            number_regex: Regex::new(r"\d+").unwrap(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.total = 0;
+        self.level_counts.clear();
+        self.per_second.clear();
+        self.messages.clear();
+    }
+
+    pub fn add(&mut self, entry: &LogEntry) {
+        self.total += 1;
+        *self.level_counts.entry(entry.level).or_insert(0) += 1;
+        if let Some(time) = entry.time {
+            *self.per_second.entry(time.and_utc().timestamp()).or_insert(0) += 1;
+        }
+        // Mask numbers so messages differing only by ids or durations group together
+        let key = self.number_regex.replace_all(entry.message.trim(), "#").to_string();
+        *self.messages.entry(key).or_insert(0) += 1;
+    }
+
+    /// Takes back an entry added before, as when it is replaced or evicted.
+    pub fn remove(&mut self, entry: &LogEntry) {
+        self.total = self.total.saturating_sub(1);
+        if let Some(count) = self.level_counts.get_mut(&entry.level) {
+            *count = count.saturating_sub(1);
+        }
+        if let Some(time) = entry.time {
+            let second = time.and_utc().timestamp();
+            if let Some(count) = self.per_second.get_mut(&second) {
+                *count -= 1;
+                if *count == 0 {
+                    self.per_second.remove(&second);
+                }
+            }
+        }
+        let key = self.number_regex.replace_all(entry.message.trim(), "#").to_string();
+        if let Some(count) = self.messages.get_mut(&key) {
+            *count -= 1;
+            if *count == 0 {
+                self.messages.remove(&key);
+            }
+        }
+    }
+
+    pub fn count(&self, level: LogLevel) -> usize {
+        self.level_counts.get(&level).copied().unwrap_or(0)
+    }
+
+    pub fn time_span(&self) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        let first = *self.per_second.keys().next()?;
+        let last = *self.per_second.keys().next_back()?;
+        Some((
+            chrono::DateTime::from_timestamp(first, 0)?.naive_utc(),
+            chrono::DateTime::from_timestamp(last, 0)?.naive_utc(),
+        ))
+    }
+
+    /// Buckets the timestamped entries into at most `max_buckets` equal time
+    /// slices. Returns the counts and the width of one bucket in seconds;
+    /// buckets wider than a minute are rounded up to whole minutes.
+    pub fn rate_histogram(&self, max_buckets: usize) -> (Vec<u64>, i64) {
+        let (Some(&first), Some(&last)) = (self.per_second.keys().next(), self.per_second.keys().next_back()) else {
+            return (Vec::new(), 1);
+        };
+        let max_buckets = max_buckets.max(1) as i64;
+        let span = last - first + 1;
+        let mut bucket_secs = ((span + max_buckets - 1) / max_buckets).max(1);
+        if bucket_secs > 60 {
+            bucket_secs = (bucket_secs + 59) / 60 * 60;
+        }
+
+        let mut buckets = vec![0u64; ((span + bucket_secs - 1) / bucket_secs) as usize];
+        for (&second, &count) in &self.per_second {
+            buckets[((second - first) / bucket_secs) as usize] += count;
+        }
+        (buckets, bucket_secs)
+    }
+
+    pub fn top_messages(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut messages: Vec<(&str, usize)> = self.messages
+            .iter()
+            .filter(|(_, &count)| count > 1)
+            .map(|(message, &count)| (message.as_str(), count))
+            .collect();
+        messages.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        messages.truncate(n);
+        messages
+    }
+}
+
+/// Formats a bucket width as a short human readable duration, e.g. "5s" or "2m".
+pub fn format_bucket(secs: i64) -> String {
+    if secs % 3600 == 0 {
+        format!("{}h", secs / 3600)
+    } else if secs % 60 == 0 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuation::Continuation;
+    use crate::log_parser::LogParser;
+
+    #[test]
+    fn removing_entries_undoes_adding_them() {
+        let content: String = (0..50)
+            .map(|i| format!("[2024-05-01 10:00:{:02}] [{}] request {} done\n", i, ["INFO", "ERROR"][i % 2], i % 3))
+            .collect();
+        let entries = LogParser::with_continuation(Continuation::default()).parse(content.as_bytes());
+        let mut stats = LogStats::new();
+        for entry in &entries {
+            stats.add(entry);
+        }
+        for entry in &entries[..40] {
+            stats.remove(entry);
+        }
+
+        let mut expected = LogStats::new();
+        for entry in &entries[40..] {
+            expected.add(entry);
+        }
+        assert_eq!(stats.total, 10);
+        assert_eq!(stats.count(LogLevel::Error), expected.count(LogLevel::Error));
+        assert_eq!(stats.time_span(), expected.time_span());
+        assert_eq!(stats.rate_histogram(5), expected.rate_histogram(5));
+        assert_eq!(stats.messages, expected.messages);
+    }
+}
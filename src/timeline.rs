@@ -0,0 +1,215 @@
+use chrono::NaiveDateTime;
+use crate::log_parser::{LogEntry, LogLevel};
+
+/// Entries bucketed by parsed timestamp into equal time slices, used to draw
+/// the timeline histogram above the log view and to jump between buckets.
+pub struct Timeline {
+    start: i64,
+    bucket_ms: i64,
+    pub buckets: Vec<Bucket>,
+    /// How many of the entries are counted.
+    entries: usize,
+    /// The time and level the last counted entry was counted with, to take
+    /// it back out when it changes.
+    last: Option<(i64, LogLevel)>,
+}
+
+#[derive(Clone, Default)]
+pub struct Bucket {
+    pub counts: [usize; 4],
+    pub first_entry: Option<usize>,
+}
+
+impl Bucket {
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    pub fn count(&self, level: LogLevel) -> usize {
+        self.counts[level as usize]
+    }
+}
+
+impl Timeline {
+    /// Builds a timeline of `width` buckets spanning the first to the last
+    /// timestamped entry. Returns `None` when no entry has a timestamp.
+    pub fn build(entries: &[LogEntry], width: usize) -> Option<Self> {
+        let start = entries.iter().filter_map(millis).min()?;
+        let end = entries.iter().filter_map(millis).max()?;
+
+        let width = width.max(1) as i64;
+        let bucket_ms = ((end - start + 1) + width - 1) / width;
+        let mut timeline = Self {
+            start,
+            bucket_ms: bucket_ms.max(1),
+            buckets: vec![Bucket::default(); width as usize],
+            entries: 0,
+            last: None,
+        };
+        timeline.extend(entries);
+        Some(timeline)
+    }
+
+    /// Brings `cached` up to date with `entries` at `width` buckets: counts
+    /// the entries appended since, or builds it again if it can't.
+    pub fn refresh(cached: &mut Option<Self>, entries: &[LogEntry], width: usize) {
+        let current = cached.as_mut().is_some_and(|timeline| timeline.buckets.len() == width.max(1) && timeline.extend(entries));
+        if !current {
+            *cached = Self::build(entries, width);
+        }
+    }
+
+    /// Counts the entries after those already counted. Later times widen
+    /// the buckets; an earlier one than the start returns false, as the
+    /// timeline then has to be built again.
+    pub fn extend(&mut self, entries: &[LogEntry]) -> bool {
+        if entries.len() < self.entries {
+            return false;
+        }
+        for (i, entry) in entries.iter().enumerate().skip(self.entries) {
+            let Some(ms) = millis(entry) else {
+                continue;
+            };
+            if ms < self.start {
+                return false;
+            }
+            while ms - self.start >= self.bucket_ms * self.buckets.len() as i64 {
+                self.widen();
+            }
+            let index = self.index_of_millis(ms);
+            let bucket = &mut self.buckets[index];
+            bucket.counts[entry.level as usize] += 1;
+            bucket.first_entry = Some(bucket.first_entry.map_or(i, |first| first.min(i)));
+        }
+        self.entries = entries.len();
+        self.last = entries.last().and_then(|entry| Some((millis(entry)?, entry.level)));
+        true
+    }
+
+    /// Counts the last entry again after it changed in place. Returns false
+    /// if the timeline has to be built again.
+    pub fn last_changed(&mut self, entries: &[LogEntry]) -> bool {
+        if self.entries != entries.len() {
+            // Not counted yet
+            return true;
+        }
+        if let Some((ms, level)) = self.last {
+            let index = self.index_of_millis(ms);
+            let bucket = &mut self.buckets[index];
+            bucket.counts[level as usize] -= 1;
+            if bucket.first_entry == Some(entries.len() - 1) {
+                bucket.first_entry = None;
+            }
+        }
+        self.entries -= 1;
+        self.extend(entries)
+    }
+
+    /// Doubles the time each bucket spans, merging neighbouring buckets
+    /// into the first half and leaving the second free for later entries.
+    fn widen(&mut self) {
+        let mut buckets = vec![Bucket::default(); self.buckets.len()];
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            let merged = &mut buckets[i / 2];
+            for (count, added) in merged.counts.iter_mut().zip(bucket.counts) {
+                *count += added;
+            }
+            merged.first_entry = match (merged.first_entry, bucket.first_entry) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        }
+        self.buckets = buckets;
+        self.bucket_ms *= 2;
+    }
+
+    fn index_of_millis(&self, ms: i64) -> usize {
+        (((ms - self.start) / self.bucket_ms).max(0) as usize).min(self.buckets.len() - 1)
+    }
+
+    pub fn bucket_of(&self, time: NaiveDateTime) -> usize {
+        self.index_of_millis(time.and_utc().timestamp_millis())
+    }
+
+    pub fn bucket_start(&self, index: usize) -> Option<NaiveDateTime> {
+        chrono::DateTime::from_timestamp_millis(self.start + index as i64 * self.bucket_ms)
+            .map(|t| t.naive_utc())
+    }
+
+    pub fn bucket_ms(&self) -> i64 {
+        self.bucket_ms
+    }
+
+    pub fn max_total(&self) -> usize {
+        self.buckets.iter().map(Bucket::total).max().unwrap_or(0)
+    }
+
+    /// Finds the nearest non-empty bucket strictly before (`forward == false`)
+    /// or after `from` and returns its index.
+    pub fn next_bucket(&self, from: usize, forward: bool) -> Option<usize> {
+        if forward {
+            (from + 1..self.buckets.len()).find(|&i| self.buckets[i].first_entry.is_some())
+        } else {
+            (0..from.min(self.buckets.len())).rev().find(|&i| self.buckets[i].first_entry.is_some())
+        }
+    }
+}
+
+fn millis(entry: &LogEntry) -> Option<i64> {
+    entry.time.map(|t| t.and_utc().timestamp_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuation::Continuation;
+    use crate::log_parser::LogParser;
+
+    fn entries(from: usize, count: usize) -> Vec<LogEntry> {
+        let content: String = (from..from + count)
+            .map(|i| format!("[2024-05-01 10:{:02}:{:02}] [{}] entry {}\n", i / 60 % 60, i % 60, ["INFO", "WARN", "ERROR"][i % 3], i))
+            .collect();
+        LogParser::with_continuation(Continuation::default()).parse(content.as_bytes())
+    }
+
+    /// Checks every bucket against a count of the entries that fall in it.
+    fn assert_counts(timeline: &Timeline, entries: &[LogEntry]) {
+        let mut expected = vec![Bucket::default(); timeline.buckets.len()];
+        for (i, entry) in entries.iter().enumerate() {
+            let bucket = &mut expected[timeline.bucket_of(entry.time.unwrap())];
+            bucket.counts[entry.level as usize] += 1;
+            bucket.first_entry.get_or_insert(i);
+        }
+        for (bucket, expected) in timeline.buckets.iter().zip(&expected) {
+            assert_eq!((bucket.counts, bucket.first_entry), (expected.counts, expected.first_entry));
+        }
+    }
+
+    #[test]
+    fn appended_entries_are_counted_in_place() {
+        let mut all = entries(0, 10);
+        let mut timeline = Timeline::build(&all, 8).unwrap();
+        let bucket_ms = timeline.bucket_ms();
+        for batch in 1..20 {
+            all.extend(entries(all.len(), batch * 7));
+            let mut cached = Some(timeline);
+            Timeline::refresh(&mut cached, &all, 8);
+            timeline = cached.unwrap();
+            assert_counts(&timeline, &all);
+        }
+        // Widened rather than built again
+        assert!(((timeline.bucket_ms() / bucket_ms) as u64).is_power_of_two() && timeline.bucket_ms() % bucket_ms == 0);
+        assert!(timeline.bucket_ms() > bucket_ms);
+    }
+
+    #[test]
+    fn changed_last_entry_is_counted_again() {
+        let mut all = entries(0, 50);
+        let mut timeline = Timeline::build(&all, 10).unwrap();
+        // Later, and at another level
+        all[49] = entries(400, 1).remove(0);
+        assert!(timeline.last_changed(&all));
+        assert_counts(&timeline, &all);
+        assert_eq!(timeline.buckets.iter().map(Bucket::total).sum::<usize>(), 50);
+    }
+}
@@ -1,33 +1,89 @@
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton, MouseEvent, MouseEventKind},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::io;
+use std::time::{Duration, Instant};
 use tui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Style},
-    widgets::{Block, Borders, List, ListItem, ListState},
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Sparkline, Table, TableState, Widget},
     text::{Span, Spans},
     Terminal,
 };
 use std::path::PathBuf;
+use crate::bookmarks::BookmarkList;
+use crate::columns::{self, Column};
+use crate::fuzzy::FileFinder;
+use crate::fields;
+use crate::keymap::{Action, Context, KeyChord, Keymap};
+use crate::expansions::Expansion;
+use crate::line_index::{self, LineIndex};
 use crate::log_parser::{LogEntry, LogLevel};
+use crate::query::QueryView;
+use crate::stats::{self, LogStats};
+use crate::timeline::Timeline;
 
 pub struct UI {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     pub file_list_state: ListState,
     pub log_list_state: ListState,
-    pub expanded_entries: std::collections::HashSet<usize>,
+    pub expanded_entries: HashSet<usize>,
+    /// The open file's expand-all state and hand-toggled entries.
+    pub expansion: Expansion,
+    pub auto_expand_errors: bool,
+    pub hidden_entries: HashSet<usize>,
     log_scroll_offset: u16,
+    pub file_finder: Option<FileFinder>,
+    pub prompt: Option<Prompt>,
+    pub bookmark_list: Option<BookmarkList>,
+    pub query_view: Option<QueryView>,
+    pending_key: Option<Action>,
+    pending_count: Option<usize>,
+    pub keymap: Keymap,
+    help_scroll: Option<u16>,
+    pub selection_anchor: Option<usize>,
+    pub show_stats: bool,
+    pub show_timeline: bool,
+    pub show_details: bool,
+    pub details_focused: bool,
+    pub detail_field: usize,
+    pub show_columns: bool,
+    pub wrap_lines: bool,
+    pub line_numbers: LineNumbers,
+    message: Option<Message>,
+    pub columns: Vec<Column>,
+    pub column_sort: Option<(String, bool)>,
+    pub row_order: Vec<usize>,
+    pub timeline_width: u16,
+    pub log_view_height: usize,
+    log_view_offset: usize,
+    pub file_list_width: Option<u16>,
+    pub file_list_hidden: bool,
+    pub file_list_min_width: u16,
+    pub file_list_max_width: u16,
+    file_list_offset: usize,
+    layout: ScreenLayout,
+    dragging_divider: bool,
+    last_click: Option<(Instant, usize)>,
+    line_index: LineIndex,
+    /// File line and byte offset of the first entry, past any evicted ones.
+    pub file_start: (usize, u64),
+    timeline: Option<Timeline>,
+    /// Set by input since the last draw.
+    pub needs_redraw: bool,
 }
 
 impl UI {
     pub fn new() -> Result<Self, io::Error> {
         enable_raw_mode()?;
         let mut stdout = io::stdout();
-        stdout.execute(EnterAlternateScreen)?;
+        stdout.execute(EnterAlternateScreen)?.execute(EnableMouseCapture)?;
         let backend = CrosstermBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
 
@@ -35,8 +91,47 @@ impl UI {
             terminal,
             file_list_state: ListState::default(),
             log_list_state: ListState::default(),
-            expanded_entries: std::collections::HashSet::new(),
+            expanded_entries: HashSet::new(),
+            expansion: Expansion::default(),
+            auto_expand_errors: false,
+            hidden_entries: HashSet::new(),
             log_scroll_offset: 0,
+            file_finder: None,
+            prompt: None,
+            bookmark_list: None,
+            query_view: None,
+            pending_key: None,
+            pending_count: None,
+            keymap: Keymap::default(),
+            help_scroll: None,
+            selection_anchor: None,
+            show_stats: false,
+            show_timeline: true,
+            show_details: false,
+            details_focused: false,
+            detail_field: 0,
+            show_columns: false,
+            wrap_lines: false,
+            line_numbers: LineNumbers::Off,
+            message: None,
+            columns: Vec::new(),
+            column_sort: None,
+            row_order: Vec::new(),
+            timeline_width: 0,
+            log_view_height: 0,
+            log_view_offset: 0,
+            file_list_width: None,
+            file_list_hidden: false,
+            file_list_min_width: MIN_FILE_LIST_WIDTH,
+            file_list_max_width: MAX_FILE_LIST_WIDTH,
+            file_list_offset: 0,
+            layout: ScreenLayout::default(),
+            dragging_divider: false,
+            last_click: None,
+            line_index: LineIndex::default(),
+            file_start: (1, 0),
+            timeline: None,
+            needs_redraw: true,
         })
     }
 
@@ -44,14 +139,51 @@ impl UI {
         &mut self,
         files: &[PathBuf],
         log_entries: &[LogEntry],
-        _current_file: &Option<PathBuf>,
         is_file_list_focused: bool,
+        // Statistics of everything loaded and, with a filter active, of what it shows
+        stats: (&LogStats, Option<&LogStats>),
+        entry_marks: &HashMap<usize, char>,
+        status: &StatusLine,
     ) -> Result<(), io::Error> {
+        self.needs_redraw = false;
+        // Transient messages fade after a few seconds
+        if self.message.as_ref().is_some_and(|m| m.shown_at.elapsed() > MESSAGE_DURATION) {
+            self.message = None;
+        }
+
+        // Entries may have been replaced or appended behind the index's back
+        if self.line_index.len() != log_entries.len() {
+            self.rebuild_line_index(log_entries);
+        }
+        let context = self.context(is_file_list_focused);
+        // Rows selected for export, if a selection is active
+        let selection = self.selected_positions();
+        // The file list keeps 10% of the width until it is resized
+        self.layout.screen_width = self.terminal.size()?.width;
+        let file_list_width = match self.file_list_width {
+            _ if self.file_list_hidden => 0,
+            Some(width) => self.clamp_file_list_width(width),
+            None => self.clamp_file_list_width(self.layout.screen_width / 10),
+        };
         self.terminal.draw(|f| {
+            let screen = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(1), Constraint::Length(1)].as_ref())
+                .split(f.size());
+            draw_hints(f, screen[1], &self.keymap, context, self.pending_count);
+            self.layout.status = screen[2];
+            self.layout.status_targets = draw_status(f, screen[2], status, self.message.as_ref());
+
             let chunks = Layout::default()
                 .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(10), Constraint::Percentage(90)].as_ref())
-                .split(f.size());
+                .constraints([Constraint::Length(file_list_width), Constraint::Min(0)].as_ref())
+                .split(screen[0]);
+            self.layout.file_list_width = chunks[0].width;
+            self.layout.divider_x = if self.file_list_hidden {
+                u16::MAX
+            } else {
+                (chunks[0].x + chunks[0].width).saturating_sub(1)
+            };
 
             // Create a custom area for the file list that's one character narrower
             let file_list_area = chunks[0];
@@ -84,6 +216,11 @@ impl UI {
                     Style::default()
                 });
             f.render_stateful_widget(file_list, file_list_area, &mut self.file_list_state);
+            // Mirror the List widget's scrolling so clicks map to the right file
+            if let Some(selected) = self.file_list_state.selected() {
+                self.file_list_offset = scroll_offset(self.file_list_offset, selected, file_list_area.height as usize, |_| 1);
+            }
+            self.layout.file_list = file_list_area;
 
             // Add a vertical line between the panels
             let vertical_line = Block::default()
@@ -91,101 +228,753 @@ impl UI {
                 .border_style(Style::default().fg(Color::DarkGray));
             f.render_widget(vertical_line, chunks[0]);
 
-            // Log entries
-            let log_items: Vec<ListItem> = log_entries
-                .iter()
-                .enumerate()
-                .flat_map(|(i, entry)| {
-                    let is_expanded = self.expanded_entries.contains(&i);
-                    let style = match entry.level {
-                        LogLevel::Debug => Style::default().fg(Color::DarkGray),
-                        LogLevel::Info => Style::default().fg(Color::White),
-                        LogLevel::Warn => Style::default().fg(Color::Yellow),
-                        LogLevel::Error => Style::default().fg(Color::Red),
-                    };
-                    if is_expanded {
-                        entry.lines.iter().enumerate().map(|(i, line)| {
-                            let line = if self.log_scroll_offset > 0 {
-                                line.chars().skip(self.log_scroll_offset as usize).collect::<String>()
-                            } else {
-                                line.clone()
-                            };
-                            let mut spans = vec![Span::styled(line, style)];
-                            if i == 0 && entry.lines.len() > 1 {
-                                spans.push(Span::styled(" ▼", Style::default().fg(Color::Cyan)));
-                            }
-                            ListItem::new(Spans::from(spans))
-                        }).collect::<Vec<_>>()
-                    } else {
-                        let mut line = entry.lines[0].clone();
-                        if self.log_scroll_offset > 0 {
-                            line = line.chars().skip(self.log_scroll_offset as usize).collect::<String>();
-                        }
-                        let mut spans = vec![Span::styled(line, style)];
-                        if entry.lines.len() > 1 {
-                            spans.push(Span::styled(" ▶", Style::default().fg(Color::Cyan)));
-                        }
-                        vec![ListItem::new(Spans::from(spans))]
-                    }
-                })
-                .collect();
+            let log_area = if self.show_stats {
+                let log_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(0), Constraint::Length(10)].as_ref())
+                    .split(chunks[1]);
+                draw_stats(f, log_chunks[1], stats.0, stats.1);
+                log_chunks[0]
+            } else {
+                chunks[1]
+            };
+
+            let (log_area, details_area) = if self.show_details {
+                let detail_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+                    .split(log_area);
+                (detail_chunks[0], Some(detail_chunks[1]))
+            } else {
+                (log_area, None)
+            };
+
+            let has_timestamps = stats.0.time_span().is_some();
+            let (timeline_area, log_area) = if self.show_timeline && has_timestamps {
+                let timeline_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(6), Constraint::Min(0)].as_ref())
+                    .split(log_area);
+                (Some(timeline_chunks[0]), timeline_chunks[1])
+            } else {
+                (None, log_area)
+            };
+
+            let index = &self.line_index;
+
+            let total_lines = index.total();
 
             // Add scroll indicator to the title
-            let scroll_indicator = if self.log_scroll_offset > 0 {
+            let mut scroll_indicator = if self.log_scroll_offset > 0 {
                 format!(" Logs (← {} →)", self.log_scroll_offset)
             } else {
                 " Logs".to_string()
             };
+            if !self.hidden_entries.is_empty() {
+                scroll_indicator.push_str(" [filtered]");
+            }
+            let log_block = Block::default().title(scroll_indicator).borders(Borders::ALL);
+            let list_area = log_block.inner(log_area);
+            f.render_widget(log_block, log_area);
+            let (header_area, list_area) = if self.show_columns {
+                let header_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+                    .split(list_area);
+                (Some(header_chunks[0]), header_chunks[1])
+            } else {
+                (None, list_area)
+            };
+
+            let list_height = list_area.height as usize;
+            self.log_view_height = list_height;
+            self.layout.log_list = list_area;
+            let selected = self.log_list_state.selected().unwrap_or(0);
+            self.log_view_offset = self.log_view_offset.min(total_lines.saturating_sub(1));
+            // Number of lines on screen, fewer than rows when lines wrap
+            let mut visible_lines = list_height;
+
+            // Bookmark gutter, only shown when the file has bookmarks
+            let gutter = |i: usize, first_line: bool| {
+                match entry_marks.get(&i) {
+                    Some(mark) if first_line => Some(Span::styled(
+                        format!("{} ", mark),
+                        Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+                    )),
+                    _ if !entry_marks.is_empty() => Some(Span::raw("  ")),
+                    _ => None,
+                }
+            };
+            let entry_style = |i: usize, entry: &LogEntry| {
+                let style = level_style(entry.level);
+                if selection.is_some_and(|(start, end)| (start..=end).contains(&index.position_of(i))) {
+                    style.bg(Color::DarkGray)
+                } else {
+                    style
+                }
+            };
+            // Source position gutter: the file line number of each line, and on demand the byte
+            // offset of each entry, which only its first line is known to start at
+            let end_position = index.file_end();
+            let number_width = end_position.0.to_string().len();
+            let offset_width = end_position.1.to_string().len();
+            let position_width = match self.line_numbers {
+                LineNumbers::Off => 0,
+                LineNumbers::Lines => number_width + 1,
+                LineNumbers::Offsets => number_width + offset_width + 3,
+            };
+            let position = |i: usize, n: usize, first_row: bool| {
+                let (line, offset) = index.file_position(i);
+                let text = match self.line_numbers {
+                    _ if !first_row => " ".repeat(position_width),
+                    LineNumbers::Lines => format!("{:>w$} ", line + n, w = number_width),
+                    LineNumbers::Offsets if n == 0 => {
+                        format!("{:>w$} @{:<ow$} ", line, offset, w = number_width, ow = offset_width)
+                    }
+                    LineNumbers::Offsets => format!("{:>w$}{:ow$}   ", line + n, "", w = number_width, ow = offset_width),
+                    LineNumbers::Off => return None,
+                };
+                Some(Span::styled(text, Style::default().fg(Color::DarkGray)))
+            };
+            let gutter_width = position_width + if entry_marks.is_empty() { 0 } else { 2 };
+            let scroll = |line: &str| line.chars().skip(self.log_scroll_offset as usize).collect::<String>();
+
+            if let Some(header_area) = header_area {
+                // Column view: only the rows on screen are laid out, since
+                // every cell needs the entry's fields extracted
+                self.log_view_offset = scroll_offset(self.log_view_offset, selected, list_height, |_| 1);
+                let mut rows = Vec::new();
+                let mut line = self.log_view_offset;
+                while line < total_lines.min(self.log_view_offset + list_height) {
+                    let i = index.entry_at_line(line);
+                    let (first_line, end) = index.lines_of_entry(i);
+                    rows.push((i, first_line));
+                    line = end;
+                }
+                let cells: Vec<Vec<String>> = rows
+                    .iter()
+                    .map(|&(i, _)| {
+                        let entry = &log_entries[i];
+                        let entry_fields = fields::extract_fields(&entry.message);
+                        self.columns
+                            .iter()
+                            .map(|c| columns::cell(entry, &entry_fields, &c.name).replace('\n', " "))
+                            .collect()
+                    })
+                    .collect();
+                let headers: Vec<String> = self.columns
+                    .iter()
+                    .map(|c| match &self.column_sort {
+                        Some((name, descending)) if *name == c.name => {
+                            format!("{} {}", c.name, if *descending { '▼' } else { '▲' })
+                        }
+                        _ => c.name.clone(),
+                    })
+                    .collect();
+                // Content-sized columns are capped so one long value can't push the rest off screen
+                let widths: Vec<usize> = self.columns
+                    .iter()
+                    .enumerate()
+                    .map(|(c, column)| match column.width {
+                        Some(width) => width as usize,
+                        None => cells
+                            .iter()
+                            .map(|row| row[c].chars().count())
+                            .chain([headers[c].chars().count()])
+                            .max()
+                            .unwrap_or(0)
+                            .min(40),
+                    })
+                    .collect();
+                let last = self.columns.len().saturating_sub(1);
+                let join = |values: &[String]| {
+                    values
+                        .iter()
+                        .enumerate()
+                        .map(|(c, value)| {
+                            // The last column takes whatever room is left
+                            if c == last && self.columns[c].width.is_none() {
+                                value.clone()
+                            } else {
+                                columns::fit(value, widths[c])
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join("  ")
+                };
+
+                let header = format!("{:gutter_width$}{}", "", scroll(&join(&headers)));
+                f.render_widget(
+                    Paragraph::new(Span::styled(header, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
+                    header_area,
+                );
+
+                let mut items = Vec::new();
+                for (&(i, first_line), row) in rows.iter().zip(&cells) {
+                    let entry = &log_entries[i];
+                    let style = entry_style(i, entry);
+                    let is_expanded = self.expanded_entries.contains(&i);
+                    let mut lines = vec![scroll(&join(row))];
+                    if is_expanded {
+                        lines.extend(entry.lines[1..].iter().map(|line| scroll(&format!("    {}", line))));
+                    }
+                    for (n, text) in lines.into_iter().enumerate() {
+                        let line = first_line + n;
+                        if line < self.log_view_offset || line >= self.log_view_offset + list_height {
+                            continue;
+                        }
+                        let mut spans: Vec<Span> = position(i, n, true).into_iter().chain(gutter(i, n == 0)).collect();
+                        spans.push(Span::styled(text, style));
+                        if let Some(marker) = expand_marker(entry, is_expanded).filter(|_| n == 0) {
+                            spans.push(Span::styled(marker, Style::default().fg(Color::Cyan)));
+                        }
+                        items.push(ListItem::new(Spans::from(spans)));
+                    }
+                }
+                self.layout.log_rows = (self.log_view_offset..total_lines.min(self.log_view_offset + list_height)).collect();
+                let mut window_state = ListState::default();
+                window_state.select(self.log_list_state.selected().map(|s| s.saturating_sub(self.log_view_offset)));
+                f.render_stateful_widget(List::new(items), list_area, &mut window_state);
+            } else {
+                // Soft wrap leaves room for the gutter and a short expand marker
+                let wrap_width = (list_area.width as usize).saturating_sub(gutter_width + 2).max(1);
+                let line_item = |i: usize, n: usize, line: &str, style: Style, marker: Option<String>| {
+                    let chunks = if self.wrap_lines { wrap_line(line, wrap_width) } else { vec![scroll(line)] };
+                    let last = chunks.len() - 1;
+                    let rows: Vec<Spans> = chunks
+                        .into_iter()
+                        .enumerate()
+                        .map(|(row, chunk)| {
+                            let mut spans: Vec<Span> = position(i, n, row == 0)
+                                .into_iter()
+                                .chain(gutter(i, n == 0 && row == 0))
+                                .collect();
+                            spans.push(Span::styled(chunk, style));
+                            if let Some(marker) = marker.clone().filter(|_| row == last) {
+                                spans.push(Span::styled(marker, Style::default().fg(Color::Cyan)));
+                            }
+                            Spans::from(spans)
+                        })
+                        .collect();
+                    ListItem::new(rows)
+                };
+                // Only the lines from the top of the view down to its bottom are laid out
+                let entry_line = |line: usize| {
+                    let i = index.entry_at_line(line);
+                    (i, line - index.lines_of_entry(i).0)
+                };
+                let line_rows = |line: usize| {
+                    let (i, n) = entry_line(line);
+                    if self.wrap_lines { wrap_line(&log_entries[i].lines[n], wrap_width).len() } else { 1 }
+                };
+                if total_lines > 0 {
+                    self.log_view_offset = scroll_offset(self.log_view_offset, selected, list_height, line_rows);
+                }
+                let mut log_items = Vec::new();
+                let mut heights = Vec::new();
+                let mut rows = 0;
+                let mut line = self.log_view_offset;
+                while line < total_lines && rows < list_height {
+                    let (i, n) = entry_line(line);
+                    let entry = &log_entries[i];
+                    let marker = expand_marker(entry, self.expanded_entries.contains(&i)).filter(|_| n == 0);
+                    let item = line_item(i, n, &entry.lines[n], entry_style(i, entry), marker);
+                    rows += item.height();
+                    heights.push(item.height());
+                    log_items.push(item);
+                    line += 1;
+                }
+
+                // Which line each row on screen belongs to, for mouse clicks
+                self.layout.log_rows = heights
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(n, &height)| std::iter::repeat_n(self.log_view_offset + n, height))
+                    .take(list_height)
+                    .collect();
+                let mut rows = 0;
+                visible_lines = heights
+                    .iter()
+                    .take_while(|&&height| {
+                        rows += height;
+                        rows <= list_height
+                    })
+                    .count();
+                let mut window_state = ListState::default();
+                window_state.select(self.log_list_state.selected().map(|s| s.saturating_sub(self.log_view_offset)));
+                f.render_stateful_widget(List::new(log_items), list_area, &mut window_state);
+            }
+
+            if let Some(area) = details_area {
+                let entry_index = index.entry_at_line(selected);
+                let entry = log_entries.get(entry_index).filter(|_| total_lines > 0);
+                let line_number = index.file_position(entry_index).0;
+                draw_details(f, area, entry, &status.file, line_number, self.detail_field, self.details_focused);
+            }
+
+            self.layout.timeline = timeline_area.map(|area| Block::default().borders(Borders::ALL).inner(area));
+            if let Some(area) = timeline_area {
+                self.timeline_width = area.width.saturating_sub(2);
+                Timeline::refresh(&mut self.timeline, log_entries, self.timeline_width as usize);
+                if let Some(timeline) = &self.timeline {
+                    let last_visible_line = (self.log_view_offset + visible_lines).min(total_lines).saturating_sub(1);
+                    let visible = (index.entry_at_line(self.log_view_offset), index.entry_at_line(last_visible_line));
+                    let selected_entry = index.entry_at_line(selected);
+                    draw_timeline(f, area, timeline, log_entries, visible, selected_entry);
+                }
+            }
+
+            if let Some(prompt) = &self.prompt {
+                let area = Rect {
+                    x: log_area.x + 1,
+                    y: (log_area.y + log_area.height).saturating_sub(4).max(log_area.y),
+                    width: log_area.width.saturating_sub(2),
+                    height: 3.min(log_area.height),
+                };
+                f.render_widget(Clear, area);
+                let title = match &prompt.error {
+                    Some(error) => Span::styled(format!(" {} ", error), Style::default().fg(Color::Red)),
+                    None => Span::raw(format!(" {} ", prompt.kind.title())),
+                };
+                let input = Paragraph::new(Spans::from(vec![
+                    Span::styled("> ", Style::default().fg(Color::Cyan)),
+                    Span::raw(prompt.input.clone()),
+                ]))
+                .block(Block::default().title(title).borders(Borders::ALL));
+                f.render_widget(input, area);
+            }
+
+            if let Some(view) = &self.query_view {
+                let area = centered_rect(90, 80, f.size());
+                f.render_widget(Clear, area);
+                let result = &view.result;
+                // Size columns to their content, capped so one wide column can't hide the rest
+                let widths: Vec<u16> = result.columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, column)| {
+                        result.rows
+                            .iter()
+                            .map(|row| row[i].to_string().chars().count())
+                            .chain([column.chars().count()])
+                            .max()
+                            .unwrap_or(0)
+                            .min(60) as u16
+                    })
+                    .collect();
+                let constraints: Vec<Constraint> = widths.iter().map(|&w| Constraint::Length(w)).collect();
+                let header = Row::new(result.columns.iter().map(|c| Cell::from(c.clone())))
+                    .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+                let rows = result.rows.iter().map(|row| {
+                    Row::new(row.iter().map(|value| Cell::from(value.to_string().replace('\n', " "))))
+                });
+                let table = Table::new(rows)
+                    .header(header)
+                    .widths(&constraints)
+                    .column_spacing(2)
+                    .block(Block::default()
+                        .title(format!(" {} ({} rows) ", view.sql, result.rows.len()))
+                        .borders(Borders::ALL))
+                    .highlight_style(Style::default().bg(Color::Blue).fg(Color::White));
+                let mut state = TableState::default();
+                if !result.rows.is_empty() {
+                    state.select(Some(view.selected));
+                }
+                f.render_stateful_widget(table, area, &mut state);
+            }
+
+            if let Some(list) = &self.bookmark_list {
+                let area = centered_rect(70, 50, f.size());
+                f.render_widget(Clear, area);
+                let items: Vec<ListItem> = list.items
+                    .iter()
+                    .map(|b| {
+                        let mut spans = vec![
+                            Span::styled(format!("{} ", b.mark), Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)),
+                            Span::styled(format!("{:>6} ", b.line), Style::default().fg(Color::DarkGray)),
+                            Span::raw(b.text.clone()),
+                        ];
+                        if !b.note.is_empty() {
+                            spans.push(Span::styled(format!("  # {}", b.note), Style::default().fg(Color::Cyan)));
+                        }
+                        ListItem::new(Spans::from(spans))
+                    })
+                    .collect();
+                let title = if list.items.is_empty() {
+                    " Bookmarks (none; set with m + letter) "
+                } else {
+                    " Bookmarks (Enter: jump, a: note, d: delete) "
+                };
+                let bookmark_list = List::new(items)
+                    .block(Block::default().title(title).borders(Borders::ALL))
+                    .highlight_style(Style::default().bg(Color::Blue).fg(Color::White));
+                let mut state = ListState::default();
+                if !list.items.is_empty() {
+                    state.select(Some(list.selected));
+                }
+                f.render_stateful_widget(bookmark_list, area, &mut state);
+            }
+
+            if let Some(finder) = &self.file_finder {
+                let area = centered_rect(60, 60, f.size());
+                f.render_widget(Clear, area);
+
+                let popup_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+                    .split(area);
 
-            let log_list = List::new(log_items)
+                let query = List::new(vec![ListItem::new(Spans::from(vec![
+                    Span::styled("> ", Style::default().fg(Color::Cyan)),
+                    Span::raw(finder.query.clone()),
+                ]))])
                 .block(Block::default()
-                    .title(scroll_indicator)
+                    .title(format!(" Find file ({}/{}) ", finder.matches.len(), files.len()))
                     .borders(Borders::ALL));
-            f.render_stateful_widget(log_list, chunks[1], &mut self.log_list_state);
+                f.render_widget(query, popup_chunks[0]);
+
+                let match_items: Vec<ListItem> = finder.matches
+                    .iter()
+                    .map(|m| {
+                        let spans: Vec<Span> = m.display
+                            .chars()
+                            .enumerate()
+                            .map(|(i, c)| {
+                                if m.positions.contains(&i) {
+                                    Span::styled(c.to_string(), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+                                } else {
+                                    Span::raw(c.to_string())
+                                }
+                            })
+                            .collect();
+                        ListItem::new(Spans::from(spans))
+                    })
+                    .collect();
+                let match_list = List::new(match_items)
+                    .block(Block::default().borders(Borders::ALL))
+                    .highlight_style(Style::default().bg(Color::Blue).fg(Color::White));
+                let mut match_state = ListState::default();
+                if !finder.matches.is_empty() {
+                    match_state.select(Some(finder.selected));
+                }
+                f.render_stateful_widget(match_list, popup_chunks[1], &mut match_state);
+            }
+
+            if let Some(scroll) = self.help_scroll.as_mut() {
+                let area = centered_rect(60, 80, f.size());
+                f.render_widget(Clear, area);
+                let lines = help_lines(&self.keymap);
+                // Stop scrolling once the last line is in view
+                *scroll = (*scroll).min((lines.len() as u16).saturating_sub(area.height.saturating_sub(2)));
+                let help = Paragraph::new(lines)
+                    .block(Block::default().title(" Keys (↑/↓ to scroll, Esc to close) ").borders(Borders::ALL))
+                    .scroll((*scroll, 0));
+                f.render_widget(help, area);
+            }
         })?;
         Ok(())
     }
 
-    pub fn handle_events(&mut self) -> Result<Option<UIEvent>, io::Error> {
-        if event::poll(std::time::Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
+    /// Waits up to `timeout` for a key and returns its event, along with the
+    /// count typed before it (as in `10j`), if any.
+    pub fn handle_events(&mut self, is_file_list_focused: bool, timeout: Duration) -> Result<Option<(UIEvent, Option<usize>)>, io::Error> {
+        if event::poll(timeout)? {
+            // Whatever it was, the screen may need to show it
+            self.needs_redraw = true;
+            let key = match event::read()? {
+                Event::Key(key) => key,
+                Event::Mouse(mouse) => return Ok(self.handle_mouse(mouse, is_file_list_focused).map(|event| (event, None))),
+                _ => return Ok(None),
+            };
+            if let Some(scroll) = self.help_scroll.as_mut() {
                 match key.code {
-                    KeyCode::Char('q') => return Ok(Some(UIEvent::Quit)),
-                    KeyCode::Esc => return Ok(Some(UIEvent::SwitchToFileList)),
-                    KeyCode::Enter => return Ok(Some(UIEvent::SwitchToLogView)),
-                    KeyCode::Up => return Ok(Some(UIEvent::Up)),
-                    KeyCode::Down => return Ok(Some(UIEvent::Down)),
-                    KeyCode::Left => return Ok(Some(UIEvent::Left)),
-                    KeyCode::Right => return Ok(Some(UIEvent::Right)),
-                    KeyCode::Char(' ') => return Ok(Some(UIEvent::ToggleExpand)),
-                    KeyCode::Char('t') => return Ok(Some(UIEvent::ToggleTail)),
-                    KeyCode::Char('h') => return Ok(Some(UIEvent::ScrollLeft)),
-                    KeyCode::Char('l') => return Ok(Some(UIEvent::ScrollRight)),
+                    KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                    KeyCode::Down => *scroll = scroll.saturating_add(1),
+                    KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+                    KeyCode::PageDown => *scroll = scroll.saturating_add(10),
+                    KeyCode::Esc | KeyCode::Char('q' | '?') => self.help_scroll = None,
                     _ => {}
                 }
+                return Ok(None);
+            }
+            if let Some(pending) = self.pending_key.take() {
+                let event = match (pending, key.code) {
+                    (Action::SetMark, KeyCode::Char(c)) if c.is_ascii_alphabetic() => Some(UIEvent::SetMark(c)),
+                    (Action::JumpToMark, KeyCode::Char(c)) if c.is_ascii_alphabetic() || c == '[' || c == ']' => {
+                        Some(UIEvent::JumpToMark(c))
+                    }
+                    (Action::NextOf, KeyCode::Char('m')) => Some(UIEvent::NextMultiline),
+                    (Action::PreviousOf, KeyCode::Char('m')) => Some(UIEvent::PreviousMultiline),
+                    (Action::NextOf, KeyCode::Char(c)) => level_of_key(c).map(UIEvent::NextLevel),
+                    (Action::PreviousOf, KeyCode::Char(c)) => level_of_key(c).map(UIEvent::PreviousLevel),
+                    _ => None,
+                };
+                return Ok(event.map(|event| (event, None)));
+            }
+            let context = self.context(is_file_list_focused);
+            // Digits build up a count for the next key, except where they are typed
+            if let (KeyCode::Char(digit @ '0'..='9'), true) = (key.code, context != Context::Popup) {
+                let digit = digit as usize - '0' as usize;
+                if key.modifiers.is_empty() && (digit > 0 || self.pending_count.is_some()) {
+                    let count = self.pending_count.unwrap_or(0).saturating_mul(10).saturating_add(digit);
+                    self.pending_count = Some(count);
+                    return Ok(None);
+                }
+            }
+            let count = self.pending_count.take();
+            match self.keymap.lookup(context, KeyChord::from_event(&key)) {
+                Some(Action::Event(event)) => return Ok(Some((event, count))),
+                Some(Action::Help) => self.help_scroll = Some(0),
+                Some(pending @ (Action::SetMark | Action::JumpToMark | Action::NextOf | Action::PreviousOf)) => {
+                    self.pending_key = Some(pending)
+                }
+                // Popups take any other character as typed input
+                None if context == Context::Popup => {
+                    if let KeyCode::Char(c) = key.code {
+                        return Ok(Some((UIEvent::Input(c), None)));
+                    }
+                }
+                None => {}
             }
         }
         Ok(None)
     }
 
+    /// Turns a mouse event into the event for whatever is under the pointer.
+    fn handle_mouse(&mut self, mouse: MouseEvent, is_file_list_focused: bool) -> Option<UIEvent> {
+        let (x, y) = (mouse.column, mouse.row);
+        let wheel = match mouse.kind {
+            MouseEventKind::ScrollUp => Some(-WHEEL_LINES),
+            MouseEventKind::ScrollDown => Some(WHEEL_LINES),
+            _ => None,
+        };
+        if let Some(scroll) = self.help_scroll.as_mut() {
+            if let Some(lines) = wheel {
+                *scroll = scroll.saturating_add_signed(lines as i16);
+            }
+            return None;
+        }
+        // Popups only scroll their list
+        if self.context(is_file_list_focused) == Context::Popup {
+            return match wheel {
+                Some(lines) if lines < 0 => Some(UIEvent::Up),
+                Some(_) => Some(UIEvent::Down),
+                None => None,
+            };
+        }
+
+        let layout = &self.layout;
+        if let Some(lines) = wheel {
+            return if contains(layout.file_list, x, y) {
+                Some(UIEvent::ScrollFiles(lines))
+            } else if contains(layout.log_list, x, y) {
+                Some(UIEvent::ScrollLines(lines))
+            } else {
+                None
+            };
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if x == layout.divider_x && y < layout.status.y {
+                    self.dragging_divider = true;
+                    None
+                } else if contains(layout.file_list, x, y) {
+                    Some(UIEvent::SelectFile(self.file_list_offset + (y - layout.file_list.y) as usize))
+                } else if contains(layout.log_list, x, y) {
+                    let line = *layout.log_rows.get((y - layout.log_list.y) as usize)?;
+                    // A second click on the same line soon after the first expands it
+                    let double_click = self.last_click.is_some_and(|(at, last)| last == line && at.elapsed() < DOUBLE_CLICK);
+                    if double_click {
+                        self.last_click = None;
+                        Some(UIEvent::ToggleExpand)
+                    } else {
+                        self.last_click = Some((Instant::now(), line));
+                        Some(UIEvent::SelectLine(line))
+                    }
+                } else if let Some(timeline) = layout.timeline.filter(|&area| contains(area, x, y)) {
+                    Some(UIEvent::JumpToBucket((x - timeline.x) as usize))
+                } else if y == layout.status.y {
+                    layout.status_targets
+                        .iter()
+                        .find(|(start, end, _)| x >= *start && x < *end)
+                        .map(|&(_, _, event)| event)
+                } else {
+                    None
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.dragging_divider => {
+                // The divider is the file list's last column
+                self.file_list_width = Some(self.clamp_file_list_width(x + 1));
+                None
+            }
+            MouseEventKind::Up(MouseButton::Left) if self.dragging_divider => {
+                self.dragging_divider = false;
+                Some(UIEvent::FileListResized)
+            }
+            _ => None,
+        }
+    }
+
+    /// Grows (or with a negative `delta`, shrinks) the file list by `delta` columns.
+    pub fn resize_file_list(&mut self, delta: i16) {
+        let width = self.file_list_width.unwrap_or(self.layout.file_list_width);
+        self.file_list_width = Some(self.clamp_file_list_width(width.saturating_add_signed(delta)));
+    }
+
+    /// Keeps a file list width within the configured limits, leaving the log view some room.
+    fn clamp_file_list_width(&self, width: u16) -> u16 {
+        let max_width = self.file_list_max_width
+            .min(self.layout.screen_width.saturating_sub(MIN_LOG_VIEW_WIDTH))
+            .max(self.file_list_min_width);
+        width.clamp(self.file_list_min_width, max_width)
+    }
+
+    /// Which bindings apply: the open popup's, or those of the focused panel.
+    fn context(&self, is_file_list_focused: bool) -> Context {
+        if self.file_finder.is_some() || self.prompt.is_some() || self.bookmark_list.is_some() || self.query_view.is_some() {
+            Context::Popup
+        } else if self.details_focused {
+            Context::Details
+        } else if is_file_list_focused {
+            Context::FileList
+        } else {
+            Context::LogView
+        }
+    }
+
+    /// Shows a message in the status bar for a few seconds.
+    pub fn show_message(&mut self, text: impl Into<String>) {
+        self.message = Some(Message { text: text.into(), is_error: false, shown_at: Instant::now() });
+    }
+
+    pub fn show_error(&mut self, text: impl Into<String>) {
+        self.message = Some(Message { text: text.into(), is_error: true, shown_at: Instant::now() });
+    }
+
+    /// Hands the terminal to another program, such as an editor.
+    pub fn suspend(&mut self) -> Result<(), io::Error> {
+        disable_raw_mode()?;
+        self.terminal.backend_mut().execute(DisableMouseCapture)?.execute(LeaveAlternateScreen)?;
+        self.terminal.show_cursor()
+    }
+
+    /// Takes the terminal back after `suspend`, redrawing everything.
+    pub fn resume(&mut self) -> Result<(), io::Error> {
+        enable_raw_mode()?;
+        self.terminal.backend_mut().execute(EnterAlternateScreen)?.execute(EnableMouseCapture)?;
+        self.terminal.hide_cursor()?;
+        self.terminal.clear()
+    }
+
     pub fn cleanup(&mut self) -> Result<(), io::Error> {
         disable_raw_mode()?;
-        self.terminal.backend_mut().execute(LeaveAlternateScreen)?;
+        self.terminal.backend_mut().execute(DisableMouseCapture)?.execute(LeaveAlternateScreen)?;
         Ok(())
     }
 
-    pub fn toggle_expand(&mut self, index: usize) {
+    pub fn toggle_expand(&mut self, log_entries: &[LogEntry], index: usize) {
         if self.expanded_entries.contains(&index) {
             self.expanded_entries.remove(&index);
         } else {
             self.expanded_entries.insert(index);
         }
+        if let Some(entry) = log_entries.get(index) {
+            let height = line_index::entry_height(entry, index, &self.expanded_entries, &self.hidden_entries);
+            self.line_index.set_height(index, height);
+            // Remembered as an exception to the expand-all and auto-expand rules
+            if entry.lines.len() > 1 && !self.expansion.toggled.remove(&entry.lines[0]) {
+                self.expansion.toggled.insert(entry.lines[0].clone());
+            }
+        }
+    }
+
+    /// Re-indexes the last indexed entry after it was read further, which
+    /// may make it multi-line and so subject to the expansion rules.
+    pub fn last_entry_changed(&mut self, log_entries: &[LogEntry]) {
+        let Some(index) = self.line_index.len().checked_sub(1) else {
+            return;
+        };
+        self.apply_expansion(log_entries, index..index + 1);
+        let height = line_index::entry_height(&log_entries[index], index, &self.expanded_entries, &self.hidden_entries);
+        self.line_index.last_entry_changed(log_entries, height);
+        if self.timeline.as_mut().is_some_and(|timeline| !timeline.last_changed(log_entries)) {
+            self.timeline = None;
+        }
     }
 
     pub fn clear_expanded_entries(&mut self) {
         self.expanded_entries.clear();
     }
 
+    /// Whether an entry is shown expanded: by expand-all or auto-expand
+    /// errors, unless it was toggled by hand against that.
+    fn is_expanded(&self, entry: &LogEntry) -> bool {
+        let by_rule = self.expansion.all || (self.auto_expand_errors && entry.level == LogLevel::Error);
+        by_rule != self.expansion.toggled.contains(&entry.lines[0])
+    }
+
+    /// Works out which of the entries in `range`, which just arrived, are
+    /// expanded. The caller re-indexes.
+    pub fn apply_expansion(&mut self, log_entries: &[LogEntry], range: Range<usize>) {
+        for i in range {
+            if log_entries[i].lines.len() > 1 && self.is_expanded(&log_entries[i]) {
+                self.expanded_entries.insert(i);
+            } else {
+                self.expanded_entries.remove(&i);
+            }
+        }
+    }
+
+    fn reapply_expansion(&mut self, log_entries: &[LogEntry]) {
+        self.apply_expansion(log_entries, 0..log_entries.len());
+        self.rebuild_line_index(log_entries);
+    }
+
+    pub fn expand_all(&mut self, log_entries: &[LogEntry]) {
+        self.expansion = Expansion { all: true, toggled: HashSet::new() };
+        self.reapply_expansion(log_entries);
+    }
+
+    /// Collapses every entry, including errors kept open by auto-expand.
+    pub fn collapse_all(&mut self, log_entries: &[LogEntry]) {
+        self.expansion = Expansion::default();
+        self.auto_expand_errors = false;
+        self.reapply_expansion(log_entries);
+    }
+
+    pub fn toggle_auto_expand_errors(&mut self, log_entries: &[LogEntry]) {
+        self.auto_expand_errors = !self.auto_expand_errors;
+        self.reapply_expansion(log_entries);
+    }
+
+    /// Re-indexes the lines of the log view after expanded or hidden entries,
+    /// the row order or the entries themselves changed.
+    pub fn rebuild_line_index(&mut self, log_entries: &[LogEntry]) {
+        self.line_index = LineIndex::build(log_entries, self.file_start, &self.row_order, &self.expanded_entries, &self.hidden_entries);
+    }
+
+    /// The timeline of the entries at the width it was last drawn, counting
+    /// any appended since it was last used.
+    pub fn timeline(&mut self, log_entries: &[LogEntry]) -> Option<&Timeline> {
+        Timeline::refresh(&mut self.timeline, log_entries, self.timeline_width as usize);
+        self.timeline.as_ref()
+    }
+
+    /// Drops the timeline, for when entries were evicted, reloaded or
+    /// filtered rather than appended.
+    pub fn invalidate_timeline(&mut self) {
+        self.timeline = None;
+    }
+
+    /// Indexes entries appended to the file, whose hidden state is already set.
+    pub fn entries_appended(&mut self, log_entries: &[LogEntry]) {
+        if self.line_index.is_sorted() {
+            self.rebuild_line_index(log_entries);
+        } else {
+            self.line_index.extend(log_entries, &self.expanded_entries, &self.hidden_entries);
+        }
+    }
+
     pub fn scroll_log_left(&mut self) {
         if self.log_scroll_offset > 0 {
             self.log_scroll_offset = self.log_scroll_offset.saturating_sub(4);
@@ -193,9 +982,35 @@ impl UI {
     }
 
     pub fn scroll_log_right(&mut self) {
+        // Wrapped lines have nothing off to the right
+        if self.wrap_lines && !self.show_columns {
+            return;
+        }
         self.log_scroll_offset = self.log_scroll_offset.saturating_add(4);
     }
 
+    /// Moves the per-entry state along after `removed` entries were dropped
+    /// from the front of the entries and `added` put there instead. The
+    /// line index is left for the caller to rebuild once entries settle.
+    pub fn shift_entries(&mut self, removed: usize, added: usize) {
+        let shift = |set: &HashSet<usize>| -> HashSet<usize> {
+            set.iter().filter(|&&i| i >= removed).map(|&i| i - removed + added).collect()
+        };
+        self.hidden_entries = shift(&self.hidden_entries);
+        self.expanded_entries = shift(&self.expanded_entries);
+        self.row_order = self.row_order.iter().filter(|&&i| i >= removed).map(|&i| i - removed + added).collect();
+        self.selection_anchor = self.selection_anchor.filter(|&i| i >= removed).map(|i| i - removed + added);
+    }
+
+    /// The entry at the top of the log view.
+    pub fn top_entry(&self) -> usize {
+        self.line_index.entry_at_line(self.log_view_offset)
+    }
+
+    pub fn set_top_entry(&mut self, entry: usize) {
+        self.log_view_offset = self.line_of_entry(entry);
+    }
+
     pub fn reset_scroll(&mut self) {
         self.log_scroll_offset = 0;
     }
@@ -203,18 +1018,661 @@ impl UI {
     pub fn is_at_beginning(&self) -> bool {
         self.log_scroll_offset == 0
     }
+
+    /// Maps a visible line index in the log view back to its log entry index,
+    /// accounting for expanded multi-line entries.
+    pub fn entry_at_line(&self, line: usize) -> usize {
+        self.line_index.entry_at_line(line)
+    }
+
+    /// Finds the nearest entry after (or before) `entry` in the order the
+    /// log view shows them that is not hidden and matches `predicate`.
+    pub fn find_entry(
+        &self,
+        log_entries: &[LogEntry],
+        entry: usize,
+        forward: bool,
+        predicate: impl Fn(&LogEntry) -> bool,
+    ) -> Option<usize> {
+        let position = self.line_index.position_of(entry);
+        let matches = |&i: &usize| !self.hidden_entries.contains(&i) && predicate(&log_entries[i]);
+        if forward {
+            (position + 1..self.line_index.len()).map(|p| self.line_index.entry_at_position(p)).find(matches)
+        } else {
+            (0..position).rev().map(|p| self.line_index.entry_at_position(p)).find(matches)
+        }
+    }
+
+    /// Display positions from the selection anchor to the selected entry,
+    /// which in a sorted view are not a range of entries.
+    fn selected_positions(&self) -> Option<(usize, usize)> {
+        let anchor = self.line_index.position_of(self.selection_anchor?);
+        let selected = self.line_index.position_of(self.entry_at_line(self.log_list_state.selected().unwrap_or(0)));
+        Some((anchor.min(selected), anchor.max(selected)))
+    }
+
+    /// The entries to export, in display order: the selected rows, or all
+    /// of them, leaving out those hidden by the filter.
+    pub fn export_entries(&self) -> Vec<usize> {
+        let Some(last) = self.line_index.len().checked_sub(1) else {
+            return Vec::new();
+        };
+        let (start, end) = self.selected_positions().unwrap_or((0, last));
+        (start..=end)
+            .map(|position| self.line_index.entry_at_position(position))
+            .filter(|entry| !self.hidden_entries.contains(entry))
+            .collect()
+    }
+
+    /// Returns the visible line index of the first line of a log entry.
+    pub fn line_of_entry(&self, entry_index: usize) -> usize {
+        self.line_index.lines_of_entry(entry_index).0
+    }
+
+    /// Returns the number of lines the log view shows, counting expanded
+    /// entries in full and skipping entries hidden by the filter.
+    pub fn total_lines(&self) -> usize {
+        self.line_index.total()
+    }
+}
+
+/// A single-line text prompt shown at the bottom of the log view.
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+    pub error: Option<String>,
+}
+
+pub enum PromptKind {
+    Goto,
+    Query,
+    BookmarkNote(char),
+    Export(usize),
+    ConfirmOverwrite(PathBuf),
+    Columns(&'static str),
+    Sort,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind) -> Self {
+        Self::with_input(kind, String::new())
+    }
+
+    pub fn with_input(kind: PromptKind, input: String) -> Self {
+        Self { kind, input, error: None }
+    }
+}
+
+impl PromptKind {
+    fn title(&self) -> String {
+        match self {
+            PromptKind::Goto => "Go to line, time (14:32:10) or offset (-5m, +30s)".to_string(),
+            PromptKind::Query => "Query, e.g. SELECT level, count(*) FROM logs GROUP BY level".to_string(),
+            PromptKind::BookmarkNote(mark) => format!("Note for bookmark '{}'", mark),
+            PromptKind::Export(count) => format!("Export {} entries to (.log, .jsonl, .csv, .html)", count),
+            PromptKind::ConfirmOverwrite(path) => format!("{} exists, overwrite? (y/n)", path.display()),
+            PromptKind::Columns(format) => format!("Columns for {} logs, e.g. time level user:12 msg", format),
+            PromptKind::Sort => "Sort by column, e.g. status desc (empty for file order)".to_string(),
+        }
+    }
+}
+
+/// What the status bar shows about the current file and view.
+pub struct StatusLine {
+    pub file: Option<PathBuf>,
+    pub size: u64,
+    pub entry: usize,
+    pub total: usize,
+    pub shown: usize,
+    pub tailing: bool,
+    pub filter: String,
+    pub format: &'static str,
+    pub rate: f64,
+    /// Entries read but not yet taken in, when ingest falls behind.
+    pub queued: usize,
+    /// Entries and bytes of them in memory, when retention is limited.
+    pub resident: Option<(usize, u64)>,
+}
+
+struct Message {
+    text: String,
+    is_error: bool,
+    shown_at: Instant,
+}
+
+const MESSAGE_DURATION: Duration = Duration::from_secs(4);
+
+/// Lines moved per mouse wheel step.
+const WHEEL_LINES: isize = 3;
+
+/// Longest gap between two clicks on a line that expands it.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Default limits of the file list width, and the room it always leaves the log view.
+pub const MIN_FILE_LIST_WIDTH: u16 = 8;
+pub const MAX_FILE_LIST_WIDTH: u16 = 60;
+const MIN_LOG_VIEW_WIDTH: u16 = 20;
+
+/// Where things were drawn in the last frame, so mouse events can be
+/// mapped back to files, lines, timeline buckets and status bar elements.
+#[derive(Default)]
+struct ScreenLayout {
+    screen_width: u16,
+    file_list: Rect,
+    file_list_width: u16,
+    divider_x: u16,
+    log_list: Rect,
+    log_rows: Vec<usize>,
+    timeline: Option<Rect>,
+    status: Rect,
+    status_targets: Vec<(u16, u16, UIEvent)>,
+}
+
+fn contains(area: Rect, x: u16, y: u16) -> bool {
+    x >= area.x && x < area.x + area.width && y >= area.y && y < area.y + area.height
 }
 
+/// What the source position gutter shows.
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineNumbers {
+    Off,
+    Lines,
+    Offsets,
+}
+
+#[derive(Clone, Copy, PartialEq)]
 pub enum UIEvent {
     Quit,
     Up,
     Down,
     Left,
     Right,
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    Top,
+    Bottom,
+    NextLevel(LogLevel),
+    PreviousLevel(LogLevel),
+    NextMultiline,
+    PreviousMultiline,
+    SelectFile(usize),
+    SelectLine(usize),
+    ScrollFiles(isize),
+    ScrollLines(isize),
+    JumpToBucket(usize),
+    GrowFileList,
+    ShrinkFileList,
+    ToggleFileList,
+    FileListResized,
     ToggleExpand,
+    ExpandAll,
+    CollapseAll,
+    ToggleAutoExpandErrors,
     ToggleTail,
     ScrollLeft,
     ScrollRight,
     SwitchToFileList,
     SwitchToLogView,
+    OpenFileFinder,
+    ToggleStats,
+    ToggleColumns,
+    ToggleWrap,
+    CycleLineNumbers,
+    OpenInEditor,
+    OpenColumns,
+    OpenSort,
+    ToggleDetails,
+    FocusDetails,
+    FilterByField,
+    ClearFilter,
+    ToggleTimeline,
+    PreviousTimeBucket,
+    NextTimeBucket,
+    OpenGoto,
+    OpenQuery,
+    SetMark(char),
+    JumpToMark(char),
+    OpenBookmarks,
+    ToggleSelection,
+    OpenExport,
+    Input(char),
+    Backspace,
+    Confirm,
+    Cancel,
+}
+
+/// Mirrors the List widget's scrolling: keeps the first line shown unless
+/// the selected line would fall outside the `list_height` rows from it.
+fn scroll_offset(offset: usize, selected: usize, list_height: usize, height: impl Fn(usize) -> usize) -> usize {
+    if selected < offset {
+        return selected;
+    }
+    let mut rows = 0;
+    let mut first = selected;
+    loop {
+        rows += height(first);
+        if rows > list_height && first < selected {
+            return first + 1;
+        }
+        if first == offset {
+            return offset;
+        }
+        first -= 1;
+    }
+}
+
+/// The marker after a multi-line entry's first line: `▼` when expanded,
+/// otherwise `▶` with the number of lines it hides.
+fn expand_marker(entry: &LogEntry, is_expanded: bool) -> Option<String> {
+    match entry.lines.len() {
+        0 | 1 => None,
+        _ if is_expanded => Some(" ▼".to_string()),
+        lines => Some(format!(" ▶ +{}", lines - 1)),
+    }
+}
+
+/// Splits a line into rows of at most `width` characters, breaking after
+/// the last space of a row when there is one.
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut rest: Vec<char> = line.chars().collect();
+    while rest.len() > width {
+        let split = match rest[..width].iter().rposition(|c| c.is_whitespace()) {
+            Some(space) if space > 0 => space + 1,
+            _ => width,
+        };
+        rows.push(rest.drain(..split).collect());
+    }
+    rows.push(rest.into_iter().collect());
+    rows
+}
+
+/// The level picked by the letter typed after `}` or `{`.
+fn level_of_key(c: char) -> Option<LogLevel> {
+    match c {
+        'e' => Some(LogLevel::Error),
+        'w' => Some(LogLevel::Warn),
+        'i' => Some(LogLevel::Info),
+        'd' => Some(LogLevel::Debug),
+        _ => None,
+    }
+}
+
+fn level_style(level: LogLevel) -> Style {
+    match level {
+        LogLevel::Debug => Style::default().fg(Color::DarkGray),
+        LogLevel::Info => Style::default().fg(Color::White),
+        LogLevel::Warn => Style::default().fg(Color::Yellow),
+        LogLevel::Error => Style::default().fg(Color::Red),
+    }
+}
+
+/// Lines of the help overlay: every binding, grouped by where it applies.
+fn help_lines(keymap: &Keymap) -> Vec<Spans<'static>> {
+    let mut lines = Vec::new();
+    for context in Context::ALL {
+        let section = keymap.section(context);
+        if section.is_empty() {
+            continue;
+        }
+        if !lines.is_empty() {
+            lines.push(Spans::from(""));
+        }
+        lines.push(Spans::from(Span::styled(context.title(), Style::default().add_modifier(Modifier::BOLD))));
+        let key_width = section.iter().map(|(keys, _)| keys.chars().count()).max().unwrap_or(0);
+        for (keys, description) in section {
+            lines.push(Spans::from(vec![
+                Span::styled(format!("  {:<w$}  ", keys, w = key_width), Style::default().fg(Color::Cyan)),
+                Span::raw(description),
+            ]));
+        }
+    }
+    lines
+}
+
+/// One line of the keys for the focused panel, as many as fit, ending with
+/// the help key.
+fn draw_hints<B: tui::backend::Backend>(f: &mut tui::Frame<B>, area: Rect, keymap: &Keymap, context: Context, count: Option<usize>) {
+    let help = keymap.keys_for(Action::Help).map(|keys| (keys, Action::Help.description()));
+    let hint_width = |(keys, description): &(String, &str)| keys.chars().count() + description.len() + 3;
+    // A count being typed is shown in front, like vim's pending command
+    let count = count.map(|count| Span::styled(format!(" {}", count), Style::default().fg(Color::Yellow)));
+    let mut width = help.as_ref().map_or(0, hint_width) + count.as_ref().map_or(0, Span::width);
+    let mut hints = Vec::new();
+    for hint in keymap.section(context) {
+        width += hint_width(&hint);
+        if width > area.width as usize {
+            break;
+        }
+        hints.push(hint);
+    }
+    let spans: Vec<Span> = count
+        .into_iter()
+        .chain(hints.into_iter()
+        .chain(help)
+        .flat_map(|(keys, description)| {
+            [
+                Span::styled(format!(" {}", keys), Style::default().fg(Color::Cyan)),
+                Span::raw(format!(" {} ", description)),
+            ]
+        }))
+        .collect();
+    f.render_widget(Paragraph::new(Spans::from(spans)).style(Style::default().fg(Color::DarkGray)), area);
+}
+
+/// Draws the status bar and returns the clickable elements: their column
+/// range and the event a click sends.
+fn draw_status<B: tui::backend::Backend>(
+    f: &mut tui::Frame<B>,
+    area: Rect,
+    status: &StatusLine,
+    message: Option<&Message>,
+) -> Vec<(u16, u16, UIEvent)> {
+    let separator = Span::styled(" │ ", Style::default().fg(Color::DarkGray));
+    let mut spans = Vec::new();
+    let mut clickable = Vec::new();
+    if let Some(file) = &status.file {
+        clickable.push((spans.len(), UIEvent::OpenFileFinder));
+        spans.push(Span::styled(format!(" {}", file.display()), Style::default().add_modifier(Modifier::BOLD)));
+        spans.push(Span::raw(format!(" ({})", format_size(status.size))));
+        spans.push(separator.clone());
+        let position = if status.total == 0 { 0 } else { status.entry + 1 };
+        clickable.push((spans.len(), UIEvent::OpenGoto));
+        spans.push(Span::raw(format!("{}/{}", position, status.total)));
+        if status.shown < status.total {
+            spans.push(Span::raw(format!(" ({} shown)", status.shown)));
+        }
+        spans.push(separator.clone());
+        clickable.push((spans.len(), UIEvent::ToggleTail));
+        spans.push(if status.tailing {
+            Span::styled("TAIL", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
+        } else {
+            Span::styled("PAUSED", Style::default().fg(Color::Yellow))
+        });
+        spans.push(separator.clone());
+        clickable.push((spans.len(), UIEvent::ToggleColumns));
+        spans.push(Span::raw(status.format));
+        spans.push(separator.clone());
+        clickable.push((spans.len(), UIEvent::ToggleStats));
+        spans.push(Span::raw(format!("{:.1} lines/s", status.rate)));
+        if status.queued > 0 {
+            spans.push(Span::styled(format!(" (+{} queued)", status.queued), Style::default().fg(Color::Yellow)));
+        }
+        if let Some((entries, bytes)) = status.resident {
+            spans.push(separator.clone());
+            spans.push(Span::raw(format!("{} in memory ({})", entries, format_size(bytes))));
+        }
+        if !status.filter.is_empty() {
+            spans.push(separator.clone());
+            clickable.push((spans.len(), UIEvent::ClearFilter));
+            spans.push(Span::styled(format!("filter: {}", status.filter), Style::default().fg(Color::Cyan)));
+        }
+    }
+    if let Some(message) = message {
+        spans.push(separator);
+        let style = if message.is_error { Style::default().fg(Color::Red) } else { Style::default().fg(Color::Green) };
+        spans.push(Span::styled(message.text.clone(), style));
+    }
+
+    let mut x = area.x;
+    let mut targets = Vec::new();
+    for (i, span) in spans.iter().enumerate() {
+        let width = span.width() as u16;
+        if let Some(&(_, event)) = clickable.iter().find(|(index, _)| *index == i) {
+            targets.push((x, x.saturating_add(width), event));
+        }
+        x = x.saturating_add(width);
+    }
+    f.render_widget(Paragraph::new(Spans::from(spans)).style(Style::default().bg(Color::Black)), area);
+    targets
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 MiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Draws the statistics panel. With a filter active, the levels show what
+/// it lets through next to the totals, and the rate and top messages cover
+/// only the filtered entries.
+fn draw_stats<B: tui::backend::Backend>(f: &mut tui::Frame<B>, area: Rect, all: &LogStats, filtered: Option<&LogStats>) {
+    let stats = filtered.unwrap_or(all);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(if filtered.is_some() { 33 } else { 24 }),
+            Constraint::Percentage(50),
+            Constraint::Percentage(50),
+        ].as_ref())
+        .split(area);
+
+    // Level distribution
+    let of_all = |total: usize| filtered.map_or(String::new(), |_| format!("{:>9}", format!("/{}", total)));
+    let mut level_lines: Vec<Spans> = LogLevel::ALL
+        .iter()
+        .map(|&level| {
+            let count = stats.count(level);
+            let percent = (count * 100).checked_div(stats.total).unwrap_or(0);
+            Spans::from(Span::styled(
+                format!("{:<6}{:>8} {:>3}%{}", level.name(), count, percent, of_all(all.count(level))),
+                level_style(level),
+            ))
+        })
+        .collect();
+    level_lines.push(Spans::from(format!("{:<6}{:>8}     {}", "TOTAL", stats.total, of_all(all.total))));
+    let title = if filtered.is_some() { " Levels (filtered/all)" } else { " Levels" };
+    let levels = Paragraph::new(level_lines)
+        .block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(levels, columns[0]);
+
+    // Entry rate over the loaded time span
+    let (buckets, bucket_secs) = stats.rate_histogram(columns[1].width.saturating_sub(2) as usize);
+    let mut rate_title = match stats.time_span() {
+        Some((first, last)) => format!(
+            " Entries per {} ({} – {}, peak {})",
+            stats::format_bucket(bucket_secs),
+            first.format("%H:%M:%S"),
+            last.format("%H:%M:%S"),
+            buckets.iter().max().copied().unwrap_or(0),
+        ),
+        None => " Entries over time (no timestamps)".to_string(),
+    };
+    if filtered.is_some() {
+        rate_title.push_str(" (filtered)");
+    }
+    let rate = Sparkline::default()
+        .block(Block::default().title(rate_title).borders(Borders::ALL))
+        .style(Style::default().fg(Color::Cyan))
+        .data(&buckets);
+    f.render_widget(rate, columns[1]);
+
+    // Top recurring messages
+    let top_items: Vec<ListItem> = stats
+        .top_messages(columns[2].height.saturating_sub(2) as usize)
+        .into_iter()
+        .map(|(message, count)| ListItem::new(format!("{:>6} {}", count, message)))
+        .collect();
+    let title = if filtered.is_some() { " Top messages (filtered)" } else { " Top messages" };
+    let top = List::new(top_items)
+        .block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(top, columns[2]);
+}
+
+fn draw_details<B: tui::backend::Backend>(
+    f: &mut tui::Frame<B>,
+    area: Rect,
+    entry: Option<&LogEntry>,
+    current_file: &Option<PathBuf>,
+    line_number: usize,
+    selected_field: usize,
+    focused: bool,
+) {
+    let border_style = if focused { Style::default().fg(Color::Blue) } else { Style::default() };
+    let block = Block::default().title(" Details").borders(Borders::ALL).border_style(border_style);
+    let Some(entry) = entry else {
+        f.render_widget(block, area);
+        return;
+    };
+
+    let key_style = Style::default().fg(Color::Cyan);
+    let row = |key: &str, value: String| {
+        Spans::from(vec![Span::styled(format!("{:<10}", key), key_style), Span::raw(value)])
+    };
+    let mut lines = vec![
+        row("time", match entry.time {
+            Some(time) => time.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+            None if entry.timestamp.is_empty() => "-".to_string(),
+            None => format!("{} (unparsed)", entry.timestamp),
+        }),
+        Spans::from(vec![
+            Span::styled(format!("{:<10}", "level"), key_style),
+            Span::styled(entry.level.name(), level_style(entry.level)),
+        ]),
+        row("file", current_file.as_ref().map(|f| f.display().to_string()).unwrap_or_default()),
+        row("line", format!("{} ({} lines)", line_number, entry.lines.len())),
+    ];
+
+    let extracted = fields::extract_fields(&entry.message);
+    if !extracted.is_empty() {
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled(
+            if focused { "Fields (f: filter by field)" } else { "Fields (Tab to select)" },
+            Style::default().add_modifier(Modifier::BOLD),
+        )));
+        let key_width = extracted.iter().map(|(k, _)| k.chars().count()).max().unwrap_or(0);
+        for (i, (key, value)) in extracted.iter().enumerate() {
+            let style = if focused && i == selected_field {
+                Style::default().bg(Color::Blue).fg(Color::White)
+            } else {
+                Style::default()
+            };
+            lines.push(Spans::from(vec![
+                Span::styled(format!("{:<w$}  ", key, w = key_width), key_style.patch(style)),
+                Span::styled(value.clone(), style),
+            ]));
+        }
+    }
+
+    if let Some(json) = fields::json_object(&entry.message) {
+        lines.push(Spans::from(""));
+        let pretty = serde_json::to_string_pretty(&json).unwrap_or_default();
+        lines.extend(pretty.lines().map(|line| Spans::from(line.to_string())));
+    }
+
+    let details = Paragraph::new(lines).block(block).wrap(tui::widgets::Wrap { trim: false });
+    f.render_widget(details, area);
+}
+
+fn draw_timeline<B: tui::backend::Backend>(
+    f: &mut tui::Frame<B>,
+    area: Rect,
+    timeline: &Timeline,
+    log_entries: &[LogEntry],
+    visible: (usize, usize),
+    selected_entry: usize,
+) {
+    let bucket_of_entry = |i: usize| log_entries.get(i).and_then(|e| e.time).map(|t| timeline.bucket_of(t));
+    // Entries without a timestamp fall back to the nearest bucket that is known
+    let visible_buckets = (
+        bucket_of_entry(visible.0).or_else(|| bucket_of_entry(visible.1)),
+        bucket_of_entry(visible.1).or_else(|| bucket_of_entry(visible.0)),
+    );
+    let selected_bucket = bucket_of_entry(selected_entry);
+
+    let title = match (timeline.bucket_start(0), timeline.bucket_start(timeline.buckets.len())) {
+        (Some(first), Some(last)) => format!(
+            " Timeline {} – {} ({} per column)",
+            first.format("%H:%M:%S"),
+            last.format("%H:%M:%S"),
+            stats::format_bucket((timeline.bucket_ms() / 1000).max(1)),
+        ),
+        _ => " Timeline".to_string(),
+    };
+    let chart = TimelineChart { timeline, visible_buckets, selected_bucket };
+    f.render_widget(chart, area);
+    let block = Block::default().title(title).borders(Borders::ALL);
+    f.render_widget(block, area);
+}
+
+struct TimelineChart<'a> {
+    timeline: &'a Timeline,
+    visible_buckets: (Option<usize>, Option<usize>),
+    selected_bucket: Option<usize>,
+}
+
+impl Widget for TimelineChart<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let inner = Block::default().borders(Borders::ALL).inner(area);
+        let max_total = self.timeline.max_total().max(1);
+        let height = inner.height as usize;
+        for (x, bucket) in self.timeline.buckets.iter().enumerate().take(inner.width as usize) {
+            let in_viewport = match self.visible_buckets {
+                (Some(first), Some(last)) => x >= first && x <= last,
+                _ => false,
+            };
+            let background = if in_viewport { Color::DarkGray } else { Color::Reset };
+
+            let total = bucket.total();
+            let bar_rows = if total > 0 { (total * height).div_ceil(max_total).max(1) } else { 0 };
+            for row in 0..height {
+                // Rows are counted from the bottom; the most severe levels are stacked lowest
+                let cell = buf.get_mut(inner.x + x as u16, inner.y + (height - 1 - row) as u16);
+                cell.set_bg(background);
+                if row < bar_rows {
+                    let level = if row == 0 && bucket.count(LogLevel::Error) > 0 {
+                        LogLevel::Error
+                    } else {
+                        let position = (row * 2 + 1) * total / (bar_rows * 2);
+                        let mut cumulative = 0;
+                        LogLevel::ALL
+                            .iter()
+                            .rev()
+                            .copied()
+                            .find(|&level| {
+                                cumulative += bucket.count(level);
+                                position < cumulative
+                            })
+                            .unwrap_or(LogLevel::Debug)
+                    };
+                    let color = level_style(level).fg.unwrap_or(Color::White);
+                    cell.set_symbol("█").set_fg(color);
+                } else if row == 0 && Some(x) == self.selected_bucket {
+                    cell.set_symbol("▲").set_fg(Color::Cyan);
+                }
+            }
+        }
+    }
+}
+
+/// Returns a rectangle of the given percentage size centred within `area`.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ].as_ref())
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ].as_ref())
+        .split(vertical[1])[1]
 } 
\ No newline at end of file